@@ -0,0 +1,82 @@
+//! Time graph::parse_with on S ::= S S | a, whose inputs a^n have
+//! Catalan(n - 1) derivations and an SPPF with O(n^3) packed nodes, against
+//! graph::parse_configured with a Recognizer, and compare the peak heap use of both.
+//!
+//! As a baseline for the keyed SPPF indices, run it again with
+//! `--features linear-scan`, which has SppfBuilder find nodes by scanning
+//! them as before.
+//!
+//! Run with `cargo bench --bench ambiguous [--features linear-scan]`.
+use gll_test::grammar::Grammar;
+use gll_test::graph::{self, Recognizer};
+use gll_test::scheduler::Scheduler;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// the system allocator, keeping track of the bytes in use and their peak
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// time f, and its peak heap use above what was in use before
+fn measure<R>(f: impl FnOnce() -> R) -> (std::time::Duration, usize) {
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    drop(result);
+    (elapsed, PEAK.load(Ordering::Relaxed) - base)
+}
+
+fn main() {
+    let mut grammar = Grammar::new();
+    let s = grammar.nonterminal("S");
+    let a = grammar.terminal("a");
+    grammar.add_production(s, vec![s, s]);
+    grammar.add_production(s, vec![a]);
+    grammar.set_start(s);
+
+    let lookup = if cfg!(feature = "linear-scan") {
+        "scanning"
+    } else {
+        "keyed"
+    };
+    eprintln!("{} SPPF node lookup", lookup);
+    for &n in &[20, 40, 80, 120] {
+        let input = vec![b'a'; n];
+        let (parse, parse_bytes) = measure(|| graph::parse_with(&grammar, &input).unwrap());
+        let (recognize, recognize_bytes) = measure(|| {
+            let scheduler = Scheduler::default();
+            let (outcome, _) =
+                graph::parse_configured(&grammar, &input, Recognizer, scheduler, &mut ());
+            outcome.unwrap()
+        });
+        eprintln!(
+            "a^{}: parse {:?}, {} KiB; recognize {:?}, {} KiB",
+            n,
+            parse,
+            parse_bytes / 1024,
+            recognize,
+            recognize_bytes / 1024
+        );
+    }
+}
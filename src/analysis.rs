@@ -0,0 +1,163 @@
+use crate::grammar::{Grammar, Slot, Symbol};
+use crate::input::Token;
+use std::collections::BTreeSet;
+
+/// A lookahead symbol: a terminal, or the end of input `$`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Lookahead {
+    Terminal(usize),
+    Eof,
+}
+
+impl Lookahead {
+    pub fn name<'g>(&self, grammar: &'g Grammar) -> &'g str {
+        match self {
+            Lookahead::Terminal(t) => &grammar.terminals[*t],
+            Lookahead::Eof => "$",
+        }
+    }
+}
+
+/// Map an input token to a lookahead, `None` being the end of input.
+pub fn token_lookahead<T: Token>(grammar: &Grammar, token: Option<&T>) -> Option<Lookahead> {
+    match token {
+        Some(token) => grammar
+            .terminals
+            .iter()
+            .position(|t| t == token.kind())
+            .map(Lookahead::Terminal),
+        None => Some(Lookahead::Eof),
+    }
+}
+
+/// Nullable, FIRST and FOLLOW sets of a grammar.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub nullable: Vec<bool>,
+    // FIRST(X) without eps
+    pub first: Vec<BTreeSet<usize>>,
+    pub follow: Vec<BTreeSet<Lookahead>>,
+    // select[p][d]: lookaheads accepted by test(x, X, β) at X ::= α . β
+    select: Vec<Vec<BTreeSet<Lookahead>>>,
+}
+
+impl Analysis {
+    pub fn new(grammar: &Grammar) -> Analysis {
+        let mut analysis = Analysis {
+            nullable: vec![false; grammar.nonterminals.len()],
+            first: vec![BTreeSet::new(); grammar.nonterminals.len()],
+            follow: vec![BTreeSet::new(); grammar.nonterminals.len()],
+            select: vec![],
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &grammar.productions {
+                let (first, nullable) = analysis.first_of(&production.rhs);
+                if nullable && !analysis.nullable[production.lhs] {
+                    analysis.nullable[production.lhs] = true;
+                    changed = true;
+                }
+                for t in first {
+                    changed |= analysis.first[production.lhs].insert(t);
+                }
+            }
+        }
+
+        analysis.follow[grammar.start].insert(Lookahead::Eof);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &grammar.productions {
+                for (index, symbol) in production.rhs.iter().enumerate() {
+                    if let Symbol::Nonterminal(y) = symbol {
+                        let (first, nullable) = analysis.first_of(&production.rhs[index + 1..]);
+                        let mut follow: BTreeSet<Lookahead> =
+                            first.into_iter().map(Lookahead::Terminal).collect();
+                        if nullable {
+                            follow.extend(analysis.follow[production.lhs].iter().cloned());
+                        }
+                        for x in follow {
+                            changed |= analysis.follow[*y].insert(x);
+                        }
+                    }
+                }
+            }
+        }
+
+        analysis.select = grammar
+            .productions
+            .iter()
+            .map(|production| {
+                (0..=production.rhs.len())
+                    .map(|dot| {
+                        let (first, nullable) = analysis.first_of(&production.rhs[dot..]);
+                        let mut select: BTreeSet<Lookahead> =
+                            first.into_iter().map(Lookahead::Terminal).collect();
+                        if nullable {
+                            select.extend(analysis.follow[production.lhs].iter().cloned());
+                        }
+                        select
+                    })
+                    .collect()
+            })
+            .collect();
+        analysis
+    }
+
+    /// FIRST(α) without eps, and whether α is nullable.
+    pub fn first_of(&self, symbols: &[Symbol]) -> (BTreeSet<usize>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            match symbol {
+                Symbol::Terminal(t) => {
+                    first.insert(*t);
+                    return (first, false);
+                }
+                Symbol::Nonterminal(x) => {
+                    first.extend(self.first[*x].iter().cloned());
+                    if !self.nullable[*x] {
+                        return (first, false);
+                    }
+                }
+                Symbol::Eps => {}
+            }
+        }
+        (first, true)
+    }
+
+    /// Lookaheads accepted at slot X ::= α . β: FIRST(β), and FOLLOW(X) if β is nullable.
+    pub fn select(&self, slot: Slot) -> &BTreeSet<Lookahead> {
+        &self.select[slot.production][slot.dot]
+    }
+
+    /// test(x, X, β) at slot X ::= α . β
+    pub fn test(&self, x: Option<Lookahead>, slot: Slot) -> bool {
+        match x {
+            Some(x) => self.select(slot).contains(&x),
+            None => false,
+        }
+    }
+}
+
+#[test]
+fn gamma1_sets() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let analysis = Analysis::new(&grammar);
+    let t = |name: &str| grammar.terminals.iter().position(|t| t == name).unwrap();
+    let set = |names: &[&str]| names.iter().map(|name| t(name)).collect::<BTreeSet<_>>();
+    // S, A, B
+    assert_eq!(analysis.nullable, [true, false, false]);
+    assert_eq!(analysis.first[0], set(&["a", "b", "c"]));
+    assert_eq!(analysis.first[1], set(&["a", "c"]));
+    assert_eq!(analysis.first[2], set(&["a", "b"]));
+    assert_eq!(
+        analysis.follow[0],
+        [Lookahead::Terminal(t("d")), Lookahead::Eof]
+            .iter()
+            .cloned()
+            .collect()
+    );
+}
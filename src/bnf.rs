@@ -0,0 +1,484 @@
+use crate::grammar::{Ebnf, Grammar, Symbol};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// Grammar text format:
+//
+//   # Γ1
+//   S ::= A S "d" | B S | ;
+//   A ::= "a" | "c" ;
+//   B ::= "a" | "b" ;
+//
+// Each rule ends with `;` and an empty alternative stands for eps. A
+// nonterminal may have several rules, and the first rule gives the start
+// symbol. Strings are terminals, one per character, so "if" is "i" "f";
+// they may contain the escapes \" \\ \n \t. Comments run from # to the
+// end of the line.
+//
+// EBNF operators are desugared into nonterminals named after their text,
+// recorded in `Grammar::ebnf`:
+//
+//   X*  X+  X?     zero or more, one or more, optional
+//   X ** s         zero or more X separated by s
+//   X ++ s         one or more X separated by s
+//   (α | β)        group
+
+/// Error in a grammar text, at a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BnfError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for BnfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for BnfError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Define,
+    Bar,
+    Semi,
+    Star,
+    Plus,
+    Question,
+    StarStar,
+    PlusPlus,
+    LParen,
+    RParen,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Define => write!(f, "::="),
+            Token::Bar => write!(f, "|"),
+            Token::Semi => write!(f, ";"),
+            Token::Star => write!(f, "*"),
+            Token::Plus => write!(f, "+"),
+            Token::Question => write!(f, "?"),
+            Token::StarStar => write!(f, "**"),
+            Token::PlusPlus => write!(f, "++"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, line: usize, column: usize, message: String) -> BnfError {
+        BnfError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    // next token and where it starts
+    fn next(&mut self) -> Result<(Token, usize, usize), BnfError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('#') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+        let (line, column) = (self.line, self.column);
+        let token = match self.bump() {
+            None => Token::End,
+            Some('|') => Token::Bar,
+            Some(';') => Token::Semi,
+            Some('?') => Token::Question,
+            Some('(') => Token::LParen,
+            Some(')') => Token::RParen,
+            Some('*') if self.peek() == Some('*') => {
+                self.bump();
+                Token::StarStar
+            }
+            Some('*') => Token::Star,
+            Some('+') if self.peek() == Some('+') => {
+                self.bump();
+                Token::PlusPlus
+            }
+            Some('+') => Token::Plus,
+            Some(':') => {
+                if self.bump() == Some(':') && self.bump() == Some('=') {
+                    Token::Define
+                } else {
+                    return Err(self.error(line, column, "expected ::=".to_owned()));
+                }
+            }
+            Some('"') => {
+                let mut s = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some('"') => s.push('"'),
+                            Some('\\') => s.push('\\'),
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            _ => {
+                                let message = "unknown escape in string".to_owned();
+                                return Err(self.error(self.line, self.column - 1, message));
+                            }
+                        },
+                        Some(c) => s.push(c),
+                        None => {
+                            let message = "unterminated string".to_owned();
+                            return Err(self.error(line, column, message));
+                        }
+                    }
+                }
+                Token::Str(s)
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = self.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        name.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Ident(name)
+            }
+            Some(c) => {
+                let message = format!("unexpected character {:?}", c);
+                return Err(self.error(line, column, message));
+            }
+        };
+        Ok((token, line, column))
+    }
+}
+
+struct Parser {
+    lexer: Lexer,
+    token: Token,
+    line: usize,
+    column: usize,
+    grammar: Grammar,
+    // nonterminals with at least one rule
+    defined: Vec<bool>,
+    // where each nonterminal was first used
+    used: HashMap<usize, (usize, usize)>,
+}
+
+impl Parser {
+    fn advance(&mut self) -> Result<(), BnfError> {
+        let (token, line, column) = self.lexer.next()?;
+        self.token = token;
+        self.line = line;
+        self.column = column;
+        Ok(())
+    }
+
+    fn error(&self, message: String) -> BnfError {
+        self.lexer.error(self.line, self.column, message)
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), BnfError> {
+        if self.token == token {
+            self.advance()
+        } else {
+            Err(self.error(format!("expected {}, found {}", token, self.token)))
+        }
+    }
+
+    fn nonterminal(&mut self, name: &str) -> usize {
+        match self.grammar.nonterminal(name) {
+            Symbol::Nonterminal(index) => {
+                if index == self.defined.len() {
+                    self.defined.push(false);
+                }
+                index
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // nonterminal for an EBNF operator, named after its text; its
+    // alternatives are only added the first time the same text is seen
+    fn synthetic<F>(&mut self, name: &str, kind: Ebnf, alternatives: F) -> Symbol
+    where
+        F: FnOnce(Symbol) -> Vec<Vec<Symbol>>,
+    {
+        let x = self.nonterminal(name);
+        let lhs = Symbol::Nonterminal(x);
+        if !self.defined[x] {
+            self.defined[x] = true;
+            self.grammar.ebnf.insert(x, kind);
+            for rhs in alternatives(lhs) {
+                self.grammar.add_production(lhs, rhs);
+            }
+        }
+        lhs
+    }
+
+    // a single symbol for a sequence, grouping it if needed
+    fn single(&mut self, symbols: Vec<Symbol>, text: &str) -> Symbol {
+        if symbols.len() == 1 {
+            symbols[0]
+        } else if text.starts_with('(') {
+            self.synthetic(text, Ebnf::Group, |_| vec![symbols])
+        } else {
+            let name = format!("({})", text);
+            self.synthetic(&name, Ebnf::Group, |_| vec![symbols])
+        }
+    }
+
+    // rule ::= ident "::=" alternatives ";"
+    fn rule(&mut self) -> Result<(), BnfError> {
+        let lhs = match &self.token {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.nonterminal(&name)
+            }
+            token => return Err(self.error(format!("expected a rule, found {}", token))),
+        };
+        self.defined[lhs] = true;
+        self.advance()?;
+        self.expect(Token::Define)?;
+        let (alternatives, _) = self.alternatives()?;
+        for rhs in alternatives {
+            self.grammar.add_production(Symbol::Nonterminal(lhs), rhs);
+        }
+        self.expect(Token::Semi)
+    }
+
+    // alternatives ::= sequence ("|" sequence)*
+    fn alternatives(&mut self) -> Result<(Vec<Vec<Symbol>>, String), BnfError> {
+        let mut alternatives = vec![];
+        let mut texts = vec![];
+        loop {
+            let (rhs, text) = self.sequence()?;
+            alternatives.push(rhs);
+            texts.push(text);
+            if self.token == Token::Bar {
+                self.advance()?;
+            } else {
+                return Ok((alternatives, texts.join(" | ")));
+            }
+        }
+    }
+
+    // sequence ::= item*
+    fn sequence(&mut self) -> Result<(Vec<Symbol>, String), BnfError> {
+        let mut rhs = vec![];
+        let mut texts = vec![];
+        while let Some((symbols, text)) = self.item()? {
+            rhs.extend(symbols);
+            texts.push(text);
+        }
+        Ok((rhs, texts.join(" ")))
+    }
+
+    // item ::= primary ("*" | "+" | "?" | "**" primary | "++" primary)*
+    fn item(&mut self) -> Result<Option<(Vec<Symbol>, String)>, BnfError> {
+        let (mut symbols, mut text) = match self.primary()? {
+            Some(primary) => primary,
+            None => return Ok(None),
+        };
+        loop {
+            let (symbol, name) = match self.token {
+                Token::Star => {
+                    let name = format!("{}*", text);
+                    let x = self.single(symbols, &text);
+                    let list = self.synthetic(&name, Ebnf::List, |n| vec![vec![], vec![x, n]]);
+                    (list, name)
+                }
+                Token::Plus => {
+                    let name = format!("{}+", text);
+                    let x = self.single(symbols, &text);
+                    let list = self.synthetic(&name, Ebnf::List, |n| vec![vec![x], vec![x, n]]);
+                    (list, name)
+                }
+                Token::Question => {
+                    let name = format!("{}?", text);
+                    let optional = self.synthetic(&name, Ebnf::Optional, |_| vec![vec![], symbols]);
+                    (optional, name)
+                }
+                Token::StarStar | Token::PlusPlus => {
+                    let star = self.token == Token::StarStar;
+                    self.advance()?;
+                    let (separator, separator_text) = match self.primary()? {
+                        Some(primary) => primary,
+                        None => {
+                            let message = format!("expected a separator, found {}", self.token);
+                            return Err(self.error(message));
+                        }
+                    };
+                    let x = self.single(symbols, &text);
+                    let s = self.single(separator, &separator_text);
+                    let name = format!("{} ++ {}", text, separator_text);
+                    let list = self.synthetic(&name, Ebnf::List, |n| vec![vec![x], vec![x, s, n]]);
+                    if star {
+                        let name = format!("{} ** {}", text, separator_text);
+                        symbols = vec![self.synthetic(&name, Ebnf::List, |_| {
+                            vec![vec![], vec![x], vec![x, s, list]]
+                        })];
+                        text = name;
+                    } else {
+                        symbols = vec![list];
+                        text = name;
+                    }
+                    // the separator was consumed by primary
+                    continue;
+                }
+                _ => return Ok(Some((symbols, text))),
+            };
+            self.advance()?;
+            symbols = vec![symbol];
+            text = name;
+        }
+    }
+
+    // primary ::= ident | string | "(" alternatives ")"
+    fn primary(&mut self) -> Result<Option<(Vec<Symbol>, String)>, BnfError> {
+        let primary = match &self.token {
+            Token::Ident(name) => {
+                let name = name.clone();
+                let x = self.nonterminal(&name);
+                self.used.entry(x).or_insert((self.line, self.column));
+                (vec![Symbol::Nonterminal(x)], name)
+            }
+            Token::Str(s) => {
+                let s = s.clone();
+                let mut symbols = vec![];
+                for c in s.chars() {
+                    if !c.is_ascii() {
+                        let message = format!("terminal {:?} is not a single byte", c);
+                        return Err(self.error(message));
+                    }
+                    symbols.push(self.grammar.terminal(&c.to_string()));
+                }
+                (symbols, format!("{:?}", s))
+            }
+            Token::LParen => {
+                self.advance()?;
+                let (mut alternatives, text) = self.alternatives()?;
+                if self.token != Token::RParen {
+                    let message = format!("expected ), found {}", self.token);
+                    return Err(self.error(message));
+                }
+                let name = format!("({})", text);
+                if alternatives.len() == 1 {
+                    // a plain sequence is only grouped when an operator needs it
+                    (alternatives.pop().unwrap(), name)
+                } else {
+                    let group = self.synthetic(&name, Ebnf::Group, |_| alternatives);
+                    (vec![group], name)
+                }
+            }
+            _ => return Ok(None),
+        };
+        self.advance()?;
+        Ok(Some(primary))
+    }
+}
+
+impl Grammar {
+    /// Read a grammar in the text format described in `bnf.rs`.
+    pub fn from_bnf(text: &str) -> Result<Grammar, BnfError> {
+        let mut parser = Parser {
+            lexer: Lexer {
+                chars: text.chars().collect(),
+                pos: 0,
+                line: 1,
+                column: 1,
+            },
+            token: Token::End,
+            line: 1,
+            column: 1,
+            grammar: Grammar::new(),
+            defined: vec![],
+            used: HashMap::new(),
+        };
+        parser.advance()?;
+        if parser.token == Token::End {
+            return Err(parser.error("expected a rule, found end of input".to_owned()));
+        }
+        while parser.token != Token::End {
+            parser.rule()?;
+        }
+        if let Some(x) = (0..parser.defined.len()).find(|&x| !parser.defined[x]) {
+            let (line, column) = parser.used[&x];
+            let name = &parser.grammar.nonterminals[x];
+            let message = format!("nonterminal {} has no rules", name);
+            return Err(parser.lexer.error(line, column, message));
+        }
+        Ok(parser.grammar)
+    }
+}
+
+#[test]
+fn gamma1_bnf() {
+    use crate::grammar::gamma1;
+    let text = r#"
+        # Γ1 from the GLL paper
+        S ::= A S "d" | B S | ;
+        A ::= "a" | "c" ;
+        B ::= "a" | "b" ;
+    "#;
+    let grammar = Grammar::from_bnf(text).unwrap();
+    let trees = |grammar: &Grammar| {
+        let sppf = crate::graph::parse_with(grammar, b"aabd").unwrap().value;
+        let mut trees: Vec<String> = sppf
+            .trees()
+            .map(|tree| tree.display(grammar).to_string())
+            .collect();
+        trees.sort();
+        trees
+    };
+    assert_eq!(trees(&grammar), trees(&gamma1()));
+
+    let error = |text| Grammar::from_bnf(text).unwrap_err().to_string();
+    assert_eq!(
+        error("S ::= \"a\" T ;\n"),
+        "1:11: nonterminal T has no rules"
+    );
+    assert_eq!(error("S ::= \"a\"\nT ::= ;"), "2:3: expected ;, found ::=");
+    assert_eq!(error("S ::= \"a ;"), "1:7: unterminated string");
+}
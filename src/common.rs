@@ -1,3 +1,10 @@
+use crate::analysis::{token_lookahead, Analysis, Lookahead};
+use crate::grammar::{gamma1, Grammar, Slot};
+use crate::input::Input;
+use crate::outcome::{Furthest, ParseError};
+use crate::span::Located;
+use std::cell::RefCell;
+
 #[derive(Debug)]
 pub enum A {
     A,
@@ -10,11 +17,101 @@ pub enum B {
 }
 #[derive(Debug)]
 pub enum S {
-    ASd(Box<A>, Box<S>),
-    BS(Box<B>, Box<S>),
+    ASd(Box<Located<A>>, Box<Located<S>>),
+    BS(Box<Located<B>>, Box<Located<S>>),
     Eps,
 }
 #[derive(Debug)]
 pub enum SS {
-    S(Box<S>),
+    S(Box<Located<S>>),
+}
+
+// productions of grammar::gamma1
+pub(crate) const S_ASD: usize = 0;
+pub(crate) const S_BS: usize = 1;
+pub(crate) const S_EPS: usize = 2;
+
+/// Lookahead tests and failure tracking for the hand-written Γ1 parsers.
+pub(crate) struct Gamma1 {
+    grammar: Grammar,
+    analysis: Analysis,
+    // length of the whole input, so positions can be recovered from suffixes
+    length: usize,
+    furthest: RefCell<Furthest>,
+}
+
+impl Gamma1 {
+    pub(crate) fn new(length: usize) -> Gamma1 {
+        let grammar = gamma1();
+        let analysis = Analysis::new(&grammar);
+        Gamma1 {
+            grammar,
+            analysis,
+            length,
+            furthest: RefCell::new(Furthest::default()),
+        }
+    }
+
+    // test(x, X, α) for the production X ::= α at the start of the remaining input
+    pub(crate) fn test(&self, input: &[u8], production: usize) -> bool {
+        let slot = Slot { production, dot: 0 };
+        if self
+            .analysis
+            .test(token_lookahead(&self.grammar, input.at(0)), slot)
+        {
+            true
+        } else {
+            let lhs = self.grammar.productions[production].lhs;
+            let expected = self
+                .analysis
+                .select(slot)
+                .iter()
+                .map(|x| x.name(&self.grammar));
+            self.furthest.borrow_mut().fail(
+                self.length - input.len(),
+                expected,
+                &self.grammar.nonterminals[lhs],
+            );
+            false
+        }
+    }
+
+    // match a terminal at the start of the remaining input
+    pub(crate) fn expect(&self, input: &[u8], terminal: u8, nonterminal: &str) -> bool {
+        if input.at(0) == Some(&terminal) {
+            true
+        } else {
+            let expected = (terminal as char).to_string();
+            self.furthest
+                .borrow_mut()
+                .fail(self.length - input.len(), Some(expected), nonterminal);
+            false
+        }
+    }
+
+    // match the end of input at the start of the remaining input
+    pub(crate) fn expect_end(&self, input: &[u8], nonterminal: &str) -> bool {
+        if input.at(0).is_none() {
+            true
+        } else {
+            let expected = Lookahead::Eof.name(&self.grammar);
+            self.furthest
+                .borrow_mut()
+                .fail(self.length - input.len(), Some(expected), nonterminal);
+            false
+        }
+    }
+
+    // node spanning len bytes from the start of the remaining input
+    pub(crate) fn located<T>(&self, input: &[u8], len: usize, node: T) -> Box<Located<T>> {
+        let start = self.length - input.len();
+        Box::new(Located {
+            node,
+            span: start..start + len,
+        })
+    }
+
+    pub(crate) fn error(self) -> ParseError {
+        self.furthest.into_inner().into_error()
+    }
 }
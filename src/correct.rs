@@ -0,0 +1,261 @@
+use crate::grammar::{Grammar, Symbol};
+use crate::graph;
+use crate::input::Token;
+use crate::outcome::ParseError;
+use crate::semiring::Tropical;
+use crate::sppf::Sppf;
+use crate::tree::Tree;
+
+/// Costs of the edits `parse_correcting` may make, all positive.
+#[derive(Clone, Debug)]
+pub struct EditCosts {
+    /// Cost of inserting a missing token, per terminal.
+    pub insert: Vec<usize>,
+    /// Cost of deleting a token, per terminal.
+    pub delete: Vec<usize>,
+    /// Cost of deleting a token that is no terminal of the grammar.
+    pub delete_other: usize,
+    /// Cost of replacing a token with another terminal, per terminal put in.
+    pub substitute: Vec<usize>,
+}
+
+impl EditCosts {
+    /// Every edit costs 1, so the cost is the number of edits.
+    pub fn uniform(grammar: &Grammar) -> EditCosts {
+        let terminals = grammar.terminals.len();
+        EditCosts {
+            insert: vec![1; terminals],
+            delete: vec![1; terminals],
+            delete_other: 1,
+            substitute: vec![1; terminals],
+        }
+    }
+}
+
+/// An edit of the input, at a token index.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Edit {
+    /// Insert the terminal before the token at `position`.
+    Insert {
+        position: usize,
+        terminal: usize,
+    },
+    Delete {
+        position: usize,
+    },
+    /// Replace the token at `position` with the terminal.
+    Substitute {
+        position: usize,
+        terminal: usize,
+    },
+}
+
+// what a production of the covering grammar stands for
+#[derive(Copy, Clone, Debug)]
+enum Cover {
+    // a production of the grammar, with each terminal a replaced by <a>
+    Production(usize),
+    // <a> ::= a
+    Match,
+    // <a> ::= b
+    Substitute(usize),
+    // <a> ::=
+    Insert(usize),
+    // <a> ::= <delete> <a>
+    Skip,
+    // <delete> ::= b
+    Delete(usize),
+    // S' ::= S <trailing>, <trailing> ::= | <delete> <trailing>
+    Start,
+}
+
+/// The result of `parse_correcting`.
+#[derive(Debug, Clone)]
+pub struct Correction<T> {
+    /// The covering grammar the input was parsed with, whose first
+    /// productions and terminals are those of the original grammar.
+    pub grammar: Grammar,
+    /// The derivations of least cost in the covering grammar.
+    pub sppf: Sppf<T>,
+    /// Total cost of the edits of each derivation.
+    pub cost: usize,
+    covers: Vec<Cover>,
+}
+
+/// Parse `input` after the fewest edits, weighted by `costs`, that make it
+/// a sentence of `grammar`, in the manner of Aho and Peterson: the input is
+/// parsed with a covering grammar where each terminal may also match a
+/// substituted token, no token, or follow deleted tokens, and the
+/// derivations of least cost are kept.
+pub fn parse_correcting<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+    costs: &EditCosts,
+) -> Result<Correction<T>, ParseError> {
+    let (covering, covers) = cover(grammar, input);
+    let weights = covers
+        .iter()
+        .map(|cover| match *cover {
+            Cover::Substitute(a) => costs.substitute[a],
+            Cover::Insert(a) => costs.insert[a],
+            Cover::Delete(b) => costs.delete.get(b).cloned().unwrap_or(costs.delete_other),
+            _ => 0,
+        } as f64)
+        .collect();
+    let tropical = Tropical { weights };
+    let sppf = graph::parse_with(&covering, input)?.value;
+    let cost = sppf.evaluate(&tropical) as usize;
+    Ok(Correction {
+        sppf: sppf.filter_best(&tropical),
+        grammar: covering,
+        cost,
+        covers,
+    })
+}
+
+// the covering grammar for the terminals of grammar and the token kinds of input
+fn cover<T: Token>(grammar: &Grammar, input: &[T]) -> (Grammar, Vec<Cover>) {
+    let mut covering = grammar.clone();
+    for token in input {
+        covering.terminal(token.kind());
+    }
+    let tokens: Vec<Symbol> = (0..covering.terminals.len())
+        .map(Symbol::Terminal)
+        .collect();
+    let covered: Vec<Symbol> = grammar
+        .terminals
+        .iter()
+        .map(|name| covering.nonterminal(&format!("<{}>", name)))
+        .collect();
+    let delete = covering.nonterminal("<delete>");
+    let trailing = covering.nonterminal("<trailing>");
+    let start = covering.nonterminal(&format!("{}'", grammar.nonterminals[grammar.start]));
+
+    let mut covers: Vec<Cover> = (0..grammar.productions.len())
+        .map(Cover::Production)
+        .collect();
+    for production in &mut covering.productions {
+        for symbol in &mut production.rhs {
+            if let Symbol::Terminal(a) = *symbol {
+                *symbol = covered[a];
+            }
+        }
+    }
+    for (a, &symbol) in covered.iter().enumerate() {
+        for &b in &tokens {
+            covering.add_production(symbol, vec![b]);
+            covers.push(if b == Symbol::Terminal(a) {
+                Cover::Match
+            } else {
+                Cover::Substitute(a)
+            });
+        }
+        covering.add_production(symbol, vec![]);
+        covers.push(Cover::Insert(a));
+        covering.add_production(symbol, vec![delete, symbol]);
+        covers.push(Cover::Skip);
+    }
+    for (b, &token) in tokens.iter().enumerate() {
+        covering.add_production(delete, vec![token]);
+        covers.push(Cover::Delete(b));
+    }
+    covering.add_production(start, vec![Symbol::Nonterminal(grammar.start), trailing]);
+    covering.add_production(trailing, vec![]);
+    covering.add_production(trailing, vec![delete, trailing]);
+    covers.extend(&[Cover::Start; 3]);
+    covering.set_start(start);
+    (covering, covers)
+}
+
+impl<T> Correction<T> {
+    /// Each derivation of least cost as a tree of the original grammar,
+    /// inserted terminals being empty leaves, with its edits in input order.
+    pub fn corrections(&self) -> impl Iterator<Item = (Tree, Vec<Edit>)> + '_ {
+        self.sppf.trees().map(move |tree| {
+            let mut trees = vec![];
+            let mut edits = vec![];
+            self.uncover(tree, &mut trees, &mut edits);
+            (trees.pop().unwrap(), edits)
+        })
+    }
+
+    // push the original tree of a covering tree, if any, and its edits
+    fn uncover(&self, tree: Tree, out: &mut Vec<Tree>, edits: &mut Vec<Edit>) {
+        let (production, extent, children) = match tree {
+            Tree::Node {
+                production,
+                extent,
+                children,
+            } => (production, extent, children),
+            tree => return out.push(tree),
+        };
+        let position = extent.0;
+        match self.covers[production] {
+            Cover::Production(production) => {
+                let mut uncovered = vec![];
+                for child in children {
+                    self.uncover(child, &mut uncovered, edits);
+                }
+                out.push(Tree::Node {
+                    production,
+                    extent,
+                    children: uncovered,
+                });
+            }
+            Cover::Match => out.extend(children),
+            Cover::Substitute(terminal) | Cover::Insert(terminal) => {
+                edits.push(match self.covers[production] {
+                    Cover::Insert(_) => Edit::Insert { position, terminal },
+                    _ => Edit::Substitute { position, terminal },
+                });
+                out.push(Tree::Leaf { terminal, extent });
+            }
+            Cover::Delete(_) => edits.push(Edit::Delete { position }),
+            Cover::Skip | Cover::Start => {
+                for child in children {
+                    self.uncover(child, out, edits);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn corrections() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let mut costs = EditCosts::uniform(&grammar);
+    let correction = parse_correcting(&grammar, b"aabd", &costs).unwrap();
+    assert_eq!(correction.cost, 0);
+    assert_eq!(correction.corrections().count(), 2);
+    assert!(correction.corrections().all(|(_, edits)| edits.is_empty()));
+
+    // unbalanced d: insert a or c in front, delete it, or make it a or b
+    let correction = parse_correcting(&grammar, b"d", &costs).unwrap();
+    assert_eq!(correction.cost, 1);
+    let edits: Vec<Vec<Edit>> = correction.corrections().map(|(_, edits)| edits).collect();
+    assert_eq!(edits.len(), 5);
+    assert!(edits.contains(&vec![Edit::Delete { position: 0 }]));
+
+    // c costs more to insert, d more to delete, and substituting most
+    costs.insert[2] = 2;
+    costs.delete = vec![3; 4];
+    costs.substitute = vec![5; 4];
+    let correction = parse_correcting(&grammar, b"dxd", &costs).unwrap();
+    assert_eq!(correction.cost, 3);
+    let corrections: Vec<(String, Vec<Edit>)> = correction
+        .corrections()
+        .map(|(tree, edits)| (tree.display(&grammar).to_string(), edits))
+        .collect();
+    let insert_a = Edit::Insert {
+        position: 0,
+        terminal: 0,
+    };
+    assert_eq!(
+        corrections,
+        [(
+            "(S (A a) (S (A a) (S) d) d)".to_owned(),
+            vec![insert_a, insert_a, Edit::Delete { position: 1 }]
+        )]
+    );
+}
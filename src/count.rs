@@ -0,0 +1,214 @@
+use crate::grammar::Symbol;
+use crate::semiring::{Counting, Values};
+use crate::sppf::{NodeId, NodeKind, Sppf};
+use crate::tree::Tree;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::Zero;
+use rand::Rng;
+use std::fmt;
+
+/// Number of derivations of an SPPF node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Count {
+    Finite(BigUint),
+    /// The node reaches a cycle of the SPPF, so it derives arbitrarily deep trees.
+    Infinite,
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Count::Finite(n) => write!(f, "{}", n),
+            Count::Infinite => write!(f, "infinitely many"),
+        }
+    }
+}
+
+/// Derivation counts of every node of an SPPF.
+pub type Counts = Values<Count>;
+
+impl Counts {
+    // count of a node below a finite root
+    fn finite(&self, node: NodeId) -> &BigUint {
+        match &self[node] {
+            Count::Finite(n) => n,
+            Count::Infinite => unreachable!(),
+        }
+    }
+}
+
+impl<T> Sppf<T> {
+    /// Count the derivations of every node by dynamic programming: a
+    /// packed node multiplies the counts of its children, symbol and
+    /// intermediate nodes add up their packed children, and terminals
+    /// and eps count once. Nodes on or above a cycle count as infinite.
+    pub fn counts(&self) -> Counts {
+        self.evaluate_all(&Counting)
+    }
+
+    /// Number of derivations of the whole input.
+    pub fn count(&self) -> Count {
+        match self.root() {
+            Some(root) => self.counts()[root].clone(),
+            None => Count::Finite(BigUint::zero()),
+        }
+    }
+}
+
+impl<T> Sppf<T> {
+    /// The derivation tree with index `n`, counting from 0, without building
+    /// the earlier ones. `None` if there are at most `n` trees, or infinitely
+    /// many; use `trees` to walk a cyclic forest.
+    pub fn nth_tree(&self, n: &BigUint) -> Option<Tree> {
+        let counts = self.counts();
+        let root = self.root()?;
+        match &counts[root] {
+            Count::Finite(count) if n < count => Some(self.unrank(&counts, root, n.clone())),
+            _ => None,
+        }
+    }
+
+    /// A derivation tree drawn uniformly at random, `None` if there are none
+    /// or infinitely many.
+    pub fn sample_tree<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Tree> {
+        let counts = self.counts();
+        let root = self.root()?;
+        match &counts[root] {
+            Count::Finite(count) if !count.is_zero() => {
+                let n = rng.gen_biguint_below(count);
+                Some(self.unrank(&counts, root, n))
+            }
+            _ => None,
+        }
+    }
+
+    // the tree with the given index below a symbol node
+    fn unrank(&self, counts: &Counts, node: NodeId, index: BigUint) -> Tree {
+        let extent = self.extent(node);
+        match self.kind(node) {
+            NodeKind::Symbol(Symbol::Terminal(terminal)) => Tree::Leaf { terminal, extent },
+            NodeKind::Error(symbol) => Tree::Error { symbol, extent },
+            _ => {
+                let (packed, index) = self.choose_packed(counts, node, index);
+                let production = self.slot(packed).unwrap().production;
+                let mut children = vec![];
+                self.unrank_packed(counts, packed, index, &mut children);
+                Tree::Node {
+                    production,
+                    extent,
+                    children,
+                }
+            }
+        }
+    }
+
+    // the packed child holding the index, and the index within it
+    fn choose_packed(
+        &self,
+        counts: &Counts,
+        node: NodeId,
+        mut index: BigUint,
+    ) -> (NodeId, BigUint) {
+        for packed in self.children(node) {
+            let count = counts.finite(packed);
+            if index < *count {
+                return (packed, index);
+            }
+            index -= count;
+        }
+        unreachable!()
+    }
+
+    // append the trees below a packed node, splitting the index over its
+    // children with the last one varying fastest
+    fn unrank_packed(
+        &self,
+        counts: &Counts,
+        packed: NodeId,
+        mut index: BigUint,
+        children: &mut Vec<Tree>,
+    ) {
+        let nodes: Vec<NodeId> = self.children(packed).collect();
+        let mut indices = vec![];
+        for &child in nodes.iter().rev() {
+            let count = counts.finite(child);
+            indices.push(&index % count);
+            index /= count;
+        }
+        for (child, index) in nodes.into_iter().zip(indices.into_iter().rev()) {
+            match self.kind(child) {
+                NodeKind::Intermediate(_) => {
+                    let (packed, index) = self.choose_packed(counts, child, index);
+                    self.unrank_packed(counts, packed, index, children);
+                }
+                NodeKind::Symbol(Symbol::Eps) => {}
+                _ => children.push(self.unrank(counts, child, index)),
+            }
+        }
+    }
+}
+
+#[test]
+fn catalan() {
+    use crate::grammar::{gamma1, Grammar};
+    use num_traits::One;
+    let sppf = crate::graph::parse(b"aabd").unwrap().value;
+    assert_eq!(sppf.count(), Count::Finite(2u32.into()));
+    assert_eq!(sppf.count(), Count::Finite(sppf.trees().count().into()));
+    assert_eq!(
+        crate::graph::parse_with(&gamma1(), b"")
+            .unwrap()
+            .value
+            .count(),
+        Count::Finite(1u32.into())
+    );
+
+    // a^n has Catalan(n - 1) derivations
+    let grammar = Grammar::from_bnf(r#"S ::= S S | "a" ;"#).unwrap();
+    let n = 60;
+    let mut catalan = BigUint::one();
+    for k in 0..n - 1 {
+        catalan = catalan * BigUint::from(2 * (2 * k + 1) as u32) / BigUint::from(k + 2);
+    }
+    let sppf = crate::graph::parse_with(&grammar, &vec![b'a'; n][..])
+        .unwrap()
+        .value;
+    assert_eq!(sppf.count(), Count::Finite(catalan));
+
+    let grammar = Grammar::from_bnf(r#"S ::= S | "a" ;"#).unwrap();
+    let sppf = crate::graph::parse_with(&grammar, b"a").unwrap().value;
+    assert_eq!(sppf.count(), Count::Infinite);
+    assert_eq!(sppf.trees().count(), 1);
+}
+
+#[test]
+fn unrank() {
+    use crate::grammar::Grammar;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::BTreeSet;
+    let grammar = Grammar::from_bnf(r#"S ::= S S | "a" ;"#).unwrap();
+    let sppf = crate::graph::parse_with(&grammar, b"aaaaaa").unwrap().value;
+    let display = |tree: Tree| tree.display(&grammar).to_string();
+    let trees: BTreeSet<String> = sppf.trees().map(display).collect();
+    let unranked: BTreeSet<String> = (0..42u32)
+        .map(|n| display(sppf.nth_tree(&n.into()).unwrap()))
+        .collect();
+    assert_eq!(trees.len(), 42);
+    assert_eq!(unranked, trees);
+    assert_eq!(sppf.nth_tree(&42u32.into()), None);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let sppf = crate::graph::parse_with(&grammar, &[b'a'; 60][..])
+        .unwrap()
+        .value;
+    let count = match sppf.count() {
+        Count::Finite(count) => count,
+        Count::Infinite => unreachable!(),
+    };
+    let last = sppf.nth_tree(&(count - 1u32)).unwrap();
+    assert_eq!(last.extent(), (0, 60));
+    assert_ne!(Some(last), sppf.nth_tree(&BigUint::zero()));
+    let tree = sppf.sample_tree(&mut rng).unwrap();
+    assert_eq!(tree.extent(), (0, 60));
+}
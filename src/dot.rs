@@ -0,0 +1,147 @@
+use crate::grammar::Grammar;
+use crate::graph::{Gss, L0};
+use crate::sppf::{NodeKind, Sppf};
+use petgraph::visit::EdgeRef;
+use std::io::{self, Write};
+
+/// What `write_sppf` and `write_gss` put in their output.
+#[derive(Clone, Copy, Debug)]
+pub struct DotOptions {
+    /// Append `(i, j)` extents to SPPF node labels, and the pivot to packed nodes.
+    pub extents: bool,
+    /// Colour SPPF nodes with more than one packed child.
+    pub ambiguity: bool,
+    /// Label GSS edges with their SPPF node.
+    pub edge_labels: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> DotOptions {
+        DotOptions {
+            extents: true,
+            ambiguity: true,
+            edge_labels: false,
+        }
+    }
+}
+
+// label of a symbol or intermediate node
+fn label(
+    grammar: &Grammar,
+    kind: NodeKind,
+    extent: (usize, usize),
+    options: &DotOptions,
+) -> String {
+    let name = match kind {
+        NodeKind::Symbol(s) => grammar.symbol_name(s).to_owned(),
+        NodeKind::Intermediate(l) => grammar.display_slot(l).to_string(),
+        NodeKind::Error(s) => format!("error {}", grammar.symbol_name(s)),
+        NodeKind::Packed(_, _) => unreachable!(),
+    };
+    if options.extents {
+        format!("{} ({}, {})", name, extent.0, extent.1)
+    } else {
+        name
+    }
+}
+
+/// Write an SPPF in DOT format: symbol nodes as ellipses, intermediate
+/// nodes as boxes, packed nodes as small circles and error nodes as
+/// octagons.
+pub fn write_sppf<W: Write, T>(
+    w: &mut W,
+    grammar: &Grammar,
+    sppf: &Sppf<T>,
+    options: &DotOptions,
+) -> io::Result<()> {
+    writeln!(w, "digraph sppf {{")?;
+    for node in sppf.nodes() {
+        let (label, shape) = match sppf.kind(node) {
+            NodeKind::Packed(_, k) if options.extents => (k.to_string(), "circle"),
+            NodeKind::Packed(_, _) => (String::new(), "circle"),
+            kind @ NodeKind::Symbol(_) => {
+                (label(grammar, kind, sppf.extent(node), options), "ellipse")
+            }
+            kind @ NodeKind::Intermediate(_) => {
+                (label(grammar, kind, sppf.extent(node), options), "box")
+            }
+            kind @ NodeKind::Error(_) => {
+                (label(grammar, kind, sppf.extent(node), options), "octagon")
+            }
+        };
+        write!(w, "    n{} [label={:?}, shape={}", node.0, label, shape)?;
+        if options.ambiguity && sppf.is_ambiguous(node) {
+            write!(w, ", color=red")?;
+        }
+        writeln!(w, "]")?;
+        for child in sppf.children(node) {
+            writeln!(w, "    n{} -> n{}", node.0, child.0)?;
+        }
+    }
+    writeln!(w, "}}")
+}
+
+/// Write a GSS in DOT format, nodes labelled with their slot and position.
+pub fn write_gss<W: Write>(
+    w: &mut W,
+    grammar: &Grammar,
+    gss: &Gss,
+    options: &DotOptions,
+) -> io::Result<()> {
+    writeln!(w, "digraph gss {{")?;
+    for index in gss.graph.node_indices() {
+        let (l, i) = gss.graph[index];
+        let label = if l == L0 {
+            format!("L0, {}", i)
+        } else {
+            format!("{}, {}", grammar.display_slot(l), i)
+        };
+        writeln!(w, "    n{} [label={:?}]", index.index(), label)?;
+    }
+    for edge in gss.graph.edge_references() {
+        write!(
+            w,
+            "    n{} -> n{}",
+            edge.source().index(),
+            edge.target().index()
+        )?;
+        if options.edge_labels {
+            let label = match *edge.weight() {
+                Some((kind, extent)) => label(grammar, kind, extent, options),
+                None => "$".to_owned(),
+            };
+            write!(w, " [label={:?}]", label)?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w, "}}")
+}
+
+#[test]
+fn gamma1_dot() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let (result, gss) = crate::graph::parse_with_gss(&grammar, b"aabd");
+    let sppf = result.unwrap().value;
+    let options = DotOptions {
+        edge_labels: true,
+        ..DotOptions::default()
+    };
+
+    let mut out = vec![];
+    write_sppf(&mut out, &grammar, &sppf, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let root = sppf.root().unwrap().0;
+    assert!(out.contains(&format!(
+        "n{} [label=\"S (0, 4)\", shape=ellipse, color=red]",
+        root
+    )));
+    assert!(out.contains("[label=\"S ::= A S . d (0, 3)\", shape=box]"));
+
+    let mut out = vec![];
+    write_gss(&mut out, &grammar, &gss, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("n0 [label=\"L0, 0\"]"));
+    assert!(out.contains("[label=\"S ::= A S . d, 1\"]"));
+    assert!(out.contains(" -> n0 [label=\"$\"]"));
+}
@@ -0,0 +1,433 @@
+use crate::grammar::{Assoc, Grammar, Mark, Symbol};
+use crate::semiring::Semiring;
+use crate::sppf::{NodeId, SPPFNode, SPPFNodeIndex, Sppf, DUMMY};
+use std::collections::HashMap;
+
+// lowest level allowed for a child, and whether that level itself is excluded
+type Context = Option<(usize, bool)>;
+
+impl<T: Clone> Sppf<T> {
+    /// Drop the derivations that break the priorities of `grammar`: a
+    /// nonterminal at the left or right end of a production with a
+    /// priority can't be derived by a production of a lower level, nor by
+    /// one of the same level on the side its associativity forbids.
+    ///
+    /// Nodes shared by parents with different constraints are copied, so
+    /// each keeps the alternatives allowed where it is used.
+    pub fn filter_priorities(&self, grammar: &Grammar) -> Sppf<T> {
+        let mut filter = PriorityFilter {
+            grammar,
+            sppf: self,
+            nodes: vec![SPPFNode::Dummy],
+            copies: HashMap::new(),
+        };
+        let root = self.root.map(|root| filter.copy(root, None));
+        Sppf {
+            nodes: filter.nodes,
+            root,
+            tokens: self.tokens.clone(),
+        }
+        .prune()
+    }
+}
+
+struct PriorityFilter<'a, T> {
+    grammar: &'a Grammar,
+    sppf: &'a Sppf<T>,
+    nodes: Vec<SPPFNode>,
+    copies: HashMap<(SPPFNodeIndex, Context), SPPFNodeIndex>,
+}
+
+impl<'a, T> PriorityFilter<'a, T> {
+    // constraint on the nonterminal at rhs[position] of a production
+    fn context(&self, production: usize, position: usize) -> Context {
+        let priority = self.grammar.priorities.get(&production)?;
+        let len = self.grammar.productions[production].rhs.len();
+        let leftmost = position == 0;
+        let rightmost = position + 1 == len;
+        if len < 2 || !(leftmost || rightmost) {
+            return None;
+        }
+        let same_level = match priority.assoc {
+            Assoc::Left => rightmost,
+            Assoc::Right => leftmost,
+            Assoc::NonAssoc => true,
+        };
+        Some((priority.level, same_level))
+    }
+
+    fn allowed(&self, context: Context, production: usize) -> bool {
+        match (context, self.grammar.priorities.get(&production)) {
+            (Some((level, same_level)), Some(priority)) => {
+                priority.level > level || (priority.level == level && !same_level)
+            }
+            _ => true,
+        }
+    }
+
+    // copy a symbol, intermediate or error node under a context
+    fn copy(&mut self, node: SPPFNodeIndex, context: Context) -> SPPFNodeIndex {
+        if let Some(&copy) = self.copies.get(&(node, context)) {
+            return copy;
+        }
+        let copy = self.nodes.len();
+        let packed_nodes = match &self.sppf.nodes[node] {
+            SPPFNode::Error(symbol, i, j) => {
+                self.nodes.push(SPPFNode::Error(*symbol, *i, *j));
+                vec![]
+            }
+            SPPFNode::Symbol(symbol, i, j, children) => {
+                self.nodes.push(SPPFNode::Symbol(*symbol, *i, *j, vec![]));
+                children.clone()
+            }
+            SPPFNode::Intermediate(slot, i, j, children) => {
+                self.nodes
+                    .push(SPPFNode::Intermediate(*slot, *i, *j, vec![]));
+                children.clone()
+            }
+            _ => unreachable!(),
+        };
+        self.copies.insert((node, context), copy);
+        for packed in packed_nodes {
+            let (slot, k, children) = match &self.sppf.nodes[packed] {
+                SPPFNode::Packed(slot, k, children) => (*slot, *k, children.clone()),
+                _ => unreachable!(),
+            };
+            if !self.allowed(context, slot.production) {
+                continue;
+            }
+            // the last child is rhs[dot - 1], a first symbol child is rhs[0]
+            let mut copies = vec![];
+            for (index, &child) in children.iter().enumerate() {
+                let context = match self.sppf.nodes[child] {
+                    SPPFNode::Symbol(Symbol::Nonterminal(_), _, _, _) => {
+                        let last = index + 1 == children.len();
+                        let position = if last { slot.dot - 1 } else { 0 };
+                        self.context(slot.production, position)
+                    }
+                    _ => None,
+                };
+                copies.push(self.copy(child, context));
+            }
+            let index = self.nodes.len();
+            self.nodes.push(SPPFNode::Packed(slot, k, copies));
+            self.nodes[copy].children_mut().unwrap().push(index);
+        }
+        copy
+    }
+}
+
+/// A packed child of a nonterminal node, given to `Sppf::filter`.
+pub struct PackedAlternative<'a, T> {
+    pub sppf: &'a Sppf<T>,
+    /// The nonterminal node.
+    pub node: NodeId,
+    /// The packed node, one derivation of `node`.
+    pub packed: NodeId,
+}
+
+impl<'a, T> PackedAlternative<'a, T> {
+    pub fn production(&self) -> usize {
+        self.sppf.slot(self.packed).unwrap().production
+    }
+
+    pub fn extent(&self) -> (usize, usize) {
+        self.sppf.extent(self.node)
+    }
+}
+
+impl<T: Clone> Sppf<T> {
+    /// Apply the reject, prefer and avoid marks of `grammar`: a node with
+    /// a rejected alternative is removed along with everything built on
+    /// it, then ambiguous nodes keep their preferred alternatives if any,
+    /// or else the ones that are not avoided if any.
+    pub fn filter_marks(&self, grammar: &Grammar) -> Sppf<T> {
+        self.select(|sppf, _, packed| {
+            let mark = |&packed: &SPPFNodeIndex| {
+                let production = sppf.slot(NodeId(packed)).unwrap().production;
+                grammar.marks.get(&production).cloned()
+            };
+            if packed.iter().any(|p| mark(p) == Some(Mark::Reject)) {
+                return vec![];
+            }
+            if packed.iter().any(|p| mark(p) == Some(Mark::Prefer)) {
+                packed
+                    .into_iter()
+                    .filter(|p| mark(p) == Some(Mark::Prefer))
+                    .collect()
+            } else if packed.iter().any(|p| mark(p) != Some(Mark::Avoid)) {
+                packed
+                    .into_iter()
+                    .filter(|p| mark(p) != Some(Mark::Avoid))
+                    .collect()
+            } else {
+                packed
+            }
+        })
+    }
+
+    /// Keep the alternatives of nonterminal nodes that `keep` accepts,
+    /// removing nodes left without any.
+    pub fn filter<F>(&self, keep: F) -> Sppf<T>
+    where
+        F: Fn(&PackedAlternative<T>) -> bool,
+    {
+        self.select(|sppf, node, packed| {
+            packed
+                .into_iter()
+                .filter(|&packed| {
+                    keep(&PackedAlternative {
+                        sppf,
+                        node,
+                        packed: NodeId(packed),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    // replace the packed children of each nonterminal node, then prune
+    fn select<F>(&self, choose: F) -> Sppf<T>
+    where
+        F: Fn(&Sppf<T>, NodeId, Vec<SPPFNodeIndex>) -> Vec<SPPFNodeIndex>,
+    {
+        let mut sppf = self.clone();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let SPPFNode::Symbol(Symbol::Nonterminal(_), _, _, packed) = node {
+                let kept = choose(self, NodeId(index), packed.clone());
+                *sppf.nodes[index].children_mut().unwrap() = kept;
+            }
+        }
+        sppf.prune()
+    }
+}
+
+// number of error nodes plus the tokens they skip
+struct ErrorCost;
+
+impl Semiring for ErrorCost {
+    type Value = usize;
+
+    fn zero(&self) -> usize {
+        usize::MAX
+    }
+
+    fn one(&self) -> usize {
+        0
+    }
+
+    fn add(&self, a: &usize, b: &usize) -> usize {
+        *a.min(b)
+    }
+
+    fn mul(&self, a: &usize, b: &usize) -> usize {
+        a.saturating_add(*b)
+    }
+
+    fn error(&self, _symbol: Symbol, (i, j): (usize, usize)) -> usize {
+        1 + j - i
+    }
+}
+
+impl<T: Clone> Sppf<T> {
+    /// Keep the derivations with the fewest and shortest error nodes, see
+    /// `graph::parse_recovering`.
+    pub fn filter_errors(&self) -> Sppf<T> {
+        self.filter_best(&ErrorCost)
+    }
+
+    /// Keep the alternatives that give each node its value, for semirings
+    /// whose `add` picks one of its operands: the cheapest derivations in
+    /// `Tropical`, the most likely ones in `MaxProduct`.
+    pub fn filter_best<S: Semiring>(&self, semiring: &S) -> Sppf<T> {
+        let values = self.evaluate_all(semiring);
+        let mut sppf = self.clone();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let (packed, extent) = match node {
+                SPPFNode::Symbol(Symbol::Nonterminal(_), i, j, packed) => (packed, Some((*i, *j))),
+                SPPFNode::Intermediate(_, _, _, packed) => (packed, None),
+                _ => continue,
+            };
+            let kept = packed
+                .iter()
+                .cloned()
+                .filter(|&packed| {
+                    let value = match extent {
+                        Some(extent) => {
+                            let production = self.slot(NodeId(packed)).unwrap().production;
+                            semiring.production(production, extent, values.values[packed].clone())
+                        }
+                        None => values.values[packed].clone(),
+                    };
+                    value == values.values[index]
+                })
+                .collect();
+            *sppf.nodes[index].children_mut().unwrap() = kept;
+        }
+        sppf.prune()
+    }
+}
+
+impl<T> Sppf<T> {
+    // remove nonterminal and intermediate nodes left without packed
+    // children, packed nodes with a removed child, and whatever is no
+    // longer reachable from the root
+    pub(crate) fn prune(self) -> Sppf<T> {
+        let nodes = self.nodes;
+        let mut alive = vec![true; nodes.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, node) in nodes.iter().enumerate() {
+                if !alive[index] {
+                    continue;
+                }
+                let dead = match node {
+                    SPPFNode::Dummy | SPPFNode::Error(_, _, _) => false,
+                    SPPFNode::Symbol(Symbol::Terminal(_), _, _, _) => false,
+                    SPPFNode::Symbol(Symbol::Eps, _, _, _) => false,
+                    SPPFNode::Symbol(_, _, _, children)
+                    | SPPFNode::Intermediate(_, _, _, children) => {
+                        !children.iter().any(|&child| alive[child])
+                    }
+                    SPPFNode::Packed(_, _, children) => children.iter().any(|&child| !alive[child]),
+                };
+                if dead {
+                    alive[index] = false;
+                    changed = true;
+                }
+            }
+        }
+
+        let root = self.root.filter(|&root| alive[root]);
+        let mut reachable = vec![false; nodes.len()];
+        reachable[DUMMY] = true;
+        let mut stack: Vec<SPPFNodeIndex> = root.into_iter().collect();
+        while let Some(node) = stack.pop() {
+            if reachable[node] {
+                continue;
+            }
+            reachable[node] = true;
+            for &child in nodes[node].children().into_iter().flatten() {
+                if alive[child] {
+                    stack.push(child);
+                }
+            }
+        }
+
+        let mut renumber = vec![DUMMY; nodes.len()];
+        let mut kept = 0;
+        for (index, &reachable) in reachable.iter().enumerate() {
+            if reachable {
+                renumber[index] = kept;
+                kept += 1;
+            }
+        }
+        let nodes = nodes
+            .into_iter()
+            .enumerate()
+            .filter(|&(index, _)| reachable[index])
+            .map(|(_, mut node)| {
+                match &mut node {
+                    SPPFNode::Dummy | SPPFNode::Error(_, _, _) => {}
+                    SPPFNode::Packed(_, _, children) => {
+                        for child in children {
+                            *child = renumber[*child];
+                        }
+                    }
+                    SPPFNode::Symbol(_, _, _, children)
+                    | SPPFNode::Intermediate(_, _, _, children) => {
+                        children.retain(|&child| alive[child]);
+                        for child in children {
+                            *child = renumber[*child];
+                        }
+                    }
+                }
+                node
+            })
+            .collect();
+        Sppf {
+            nodes,
+            root: root.map(|root| renumber[root]),
+            tokens: self.tokens,
+        }
+    }
+}
+
+#[test]
+fn priorities() {
+    let text = r#"E ::= E "=" E | E "+" E | E "*" E | "-" E | "n" ;"#;
+    let mut grammar = Grammar::from_bnf(text).unwrap();
+    grammar.set_priority(0, 0, Assoc::NonAssoc);
+    grammar.set_priority(1, 1, Assoc::Left);
+    grammar.set_priority(2, 2, Assoc::Left);
+    grammar.set_priority(3, 3, Assoc::Right);
+    let parse = |input: &[u8]| {
+        let sppf = crate::graph::parse_with(&grammar, input).unwrap().value;
+        let trees: Vec<String> = sppf
+            .trees()
+            .map(|tree| tree.display(&grammar).to_string())
+            .collect();
+        trees
+    };
+    assert_eq!(parse(b"n+n*n"), ["(E (E n) + (E (E n) * (E n)))"]);
+    assert_eq!(parse(b"n*n+n"), ["(E (E (E n) * (E n)) + (E n))"]);
+    assert_eq!(parse(b"n+n+n"), ["(E (E (E n) + (E n)) + (E n))"]);
+    assert_eq!(parse(b"-n*n"), ["(E (E - (E n)) * (E n))"]);
+    assert_eq!(parse(b"n+n*n=n").len(), 1);
+    assert_eq!(
+        crate::graph::parse_with(&grammar, b"n*n+n*n*n")
+            .unwrap()
+            .value
+            .count(),
+        crate::count::Count::Finite(1u32.into())
+    );
+    assert!(crate::graph::parse_with(&grammar, b"n=n=n").is_err());
+}
+
+#[test]
+fn marks() {
+    use crate::tree::Tree;
+    let text = r#"
+        S ::= "i" "f" E | Id "=" E ;
+        E ::= Id | Id "(" ")" | E "(" ")" ;
+        Id ::= ("i" | "f" | "x")+ | "i" "f" ;
+    "#;
+    let mut grammar = Grammar::from_bnf(text).unwrap();
+    let trees = |grammar: &Grammar, input: &[u8]| {
+        let sppf = crate::graph::parse_with(grammar, input).unwrap().value;
+        sppf.trees().count()
+    };
+    assert_eq!(trees(&grammar, b"ifx()"), 2);
+    assert_eq!(trees(&grammar, b"if=x"), 2);
+
+    // E ::= Id "(" ")"
+    let call = grammar.alternatives()[1][1];
+    let sppf = crate::graph::parse_with(&grammar, b"x=x()()")
+        .unwrap()
+        .value;
+    assert_eq!(sppf.trees().count(), 2);
+    let filtered = sppf.filter(|alternative| alternative.production() != call);
+    assert_eq!(filtered.trees().count(), 1);
+
+    // Id ::= "i" "f"
+    let keyword = grammar.alternatives()[2][1];
+    grammar.set_mark(keyword, Mark::Reject);
+    assert!(crate::graph::parse_with(&grammar, b"if=x").is_err());
+    assert_eq!(trees(&grammar, b"iff=x"), 1);
+    assert_eq!(trees(&grammar, b"ifx()"), 2);
+
+    grammar.set_mark(call, Mark::Avoid);
+    assert_eq!(trees(&grammar, b"ifx()"), 1);
+    grammar.set_mark(call, Mark::Prefer);
+    let sppf = crate::graph::parse_with(&grammar, b"ifx()").unwrap().value;
+    let trees: Vec<Tree> = sppf.trees().collect();
+    assert_eq!(trees.len(), 1);
+    match &trees[0] {
+        Tree::Node { children, .. } => match &children[2] {
+            Tree::Node { production, .. } => assert_eq!(*production, call),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
@@ -1,67 +1,72 @@
 use crate::common::*;
+use crate::outcome::{ParseError, ParseOutcome};
 use futures::executor::block_on;
-use futures::future::{BoxFuture, FutureExt};
+use futures::future::{FutureExt, LocalBoxFuture};
 
-async fn parse_ss(input: &[u8]) -> Option<(SS, usize)> {
-    if input[0] == b'a'
-        || input[0] == b'b'
-        || input[0] == b'c'
-        || input[0] == b'd'
-        || input[0] == b'$'
-    {
-        if let Some((s, len)) = parse_s(&input[..]).await {
-            if input[len] == b'$' {
-                return Some((SS::S(Box::new(s)), len));
+async fn parse_ss(g: &Gamma1, input: &[u8]) -> Option<(SS, usize)> {
+    // FIRST(S $)
+    if g.test(input, S_ASD) || g.test(input, S_BS) || g.test(input, S_EPS) {
+        if let Some((s, len)) = parse_s(g, &input[..]).await {
+            if g.expect_end(&input[len..], "SS") {
+                return Some((SS::S(g.located(input, len, s)), len));
             }
         }
     }
     return None;
 }
 
-fn parse_s<'a>(input: &'a [u8]) -> BoxFuture<'a, Option<(S, usize)>> {
+fn parse_s<'a>(g: &'a Gamma1, input: &'a [u8]) -> LocalBoxFuture<'a, Option<(S, usize)>> {
     async move {
-        if input[0] == b'a' || input[0] == b'c' {
-            if let Some((a, len_a)) = parse_a(&input[0..]).await {
-                if let Some((s, len_s)) = parse_s(&input[len_a..]).await {
-                    if input[len_a + len_s] == b'd' {
-                        return Some((S::ASd(Box::new(a), Box::new(s)), 1 + len_a + len_s));
+        if g.test(input, S_ASD) {
+            if let Some((a, len_a)) = parse_a(g, &input[0..]).await {
+                if let Some((s, len_s)) = parse_s(g, &input[len_a..]).await {
+                    if g.expect(&input[len_a + len_s..], b'd', "S") {
+                        let a = g.located(input, len_a, a);
+                        let s = g.located(&input[len_a..], len_s, s);
+                        return Some((S::ASd(a, s), 1 + len_a + len_s));
                     }
                 }
             }
         }
-        if input[0] == b'a' || input[0] == b'b' {
-            if let Some((b, len_b)) = parse_b(&input[0..]).await {
-                if let Some((s, len_s)) = parse_s(&input[len_b..]).await {
-                    return Some((S::BS(Box::new(b), Box::new(s)), len_b + len_s));
+        if g.test(input, S_BS) {
+            if let Some((b, len_b)) = parse_b(g, &input[0..]).await {
+                if let Some((s, len_s)) = parse_s(g, &input[len_b..]).await {
+                    let b = g.located(input, len_b, b);
+                    let s = g.located(&input[len_b..], len_s, s);
+                    return Some((S::BS(b, s), len_b + len_s));
                 }
             }
         }
         return Some((S::Eps, 0));
     }
-    .boxed()
+    .boxed_local()
 }
 
-async fn parse_a(input: &[u8]) -> Option<(A, usize)> {
-    if input[0] == b'a' {
+async fn parse_a(g: &Gamma1, input: &[u8]) -> Option<(A, usize)> {
+    if g.expect(input, b'a', "A") {
         return Some((A::A, 1));
     }
-    if input[0] == b'c' {
+    if g.expect(input, b'c', "A") {
         return Some((A::C, 1));
     }
     return None;
 }
 
-async fn parse_b(input: &[u8]) -> Option<(B, usize)> {
-    if input[0] == b'a' {
+async fn parse_b(g: &Gamma1, input: &[u8]) -> Option<(B, usize)> {
+    if g.expect(input, b'a', "B") {
         return Some((B::A, 1));
     }
-    if input[0] == b'b' {
+    if g.expect(input, b'b', "B") {
         return Some((B::B, 1));
     }
     return None;
 }
 
-pub fn parse(input: &[u8]) {
-    let future = parse_ss(input);
-    println!("{:?}", block_on(future));
+pub fn parse(input: &[u8]) -> Result<ParseOutcome<SS>, ParseError> {
+    let g = Gamma1::new(input.len());
+    let future = parse_ss(&g, input);
+    match block_on(future) {
+        Some((value, end)) => Ok(ParseOutcome { value, end }),
+        None => Err(g.error()),
+    }
 }
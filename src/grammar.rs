@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A grammar symbol. Terminals and nonterminals refer to
+/// `Grammar::terminals` and `Grammar::nonterminals` by index.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Symbol {
+    Terminal(usize),
+    Nonterminal(usize),
+    // eps, only used for SPPF leaves
+    Eps,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Production {
+    pub lhs: usize,
+    pub rhs: Vec<Symbol>,
+}
+
+/// A grammar slot `X ::= α . β`: the production index and the length of α.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Slot {
+    pub production: usize,
+    pub dot: usize,
+}
+
+/// What an EBNF operator was desugared into, for nonterminals introduced
+/// by `Grammar::from_bnf`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ebnf {
+    /// `(α | β)`: X ::= α | β
+    Group,
+    /// `α?`: X ::= | α
+    Optional,
+    /// `α*`: X ::= | α X, `α+`: X ::= α | α X, `α ++ s`: X ::= α | α s X,
+    /// `α ** s`: X ::= | α | α s (α ++ s)
+    List,
+}
+
+/// How a production with a priority nests with itself and with other
+/// productions of the same level.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Assoc {
+    /// `a + b + c` is `(a + b) + c`
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+    /// `a == b == c` is not allowed
+    NonAssoc,
+}
+
+/// Priority of a production: it binds tighter than productions of a lower
+/// level.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Priority {
+    pub level: usize,
+    pub assoc: Assoc,
+}
+
+/// SDF-style disambiguation marks on productions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mark {
+    /// Nothing the production derives is derived by its nonterminal, e.g.
+    /// `Id ::= "if"` keeps keywords out of identifiers.
+    Reject,
+    /// Among the alternatives of an ambiguous node, only keep those using
+    /// preferred productions.
+    Prefer,
+    /// Among the alternatives of an ambiguous node, drop those using
+    /// avoided productions, unless nothing else is left.
+    Avoid,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Grammar {
+    pub nonterminals: Vec<String>,
+    pub terminals: Vec<String>,
+    pub productions: Vec<Production>,
+    pub start: usize,
+    /// Nonterminals introduced for EBNF operators.
+    pub ebnf: BTreeMap<usize, Ebnf>,
+    /// Priorities of productions, enforced by `graph::parse_with`.
+    pub priorities: BTreeMap<usize, Priority>,
+    /// Disambiguation marks of productions, enforced by `graph::parse_with`.
+    pub marks: BTreeMap<usize, Mark>,
+}
+
+impl Grammar {
+    pub fn new() -> Grammar {
+        Grammar::default()
+    }
+
+    /// Find or add the nonterminal `name`.
+    pub fn nonterminal(&mut self, name: &str) -> Symbol {
+        if let Some(index) = self.nonterminals.iter().position(|n| n == name) {
+            Symbol::Nonterminal(index)
+        } else {
+            self.nonterminals.push(name.to_owned());
+            Symbol::Nonterminal(self.nonterminals.len() - 1)
+        }
+    }
+
+    /// Find or add the terminal `name`.
+    pub fn terminal(&mut self, name: &str) -> Symbol {
+        if let Some(index) = self.terminals.iter().position(|t| t == name) {
+            Symbol::Terminal(index)
+        } else {
+            self.terminals.push(name.to_owned());
+            Symbol::Terminal(self.terminals.len() - 1)
+        }
+    }
+
+    pub fn set_start(&mut self, start: Symbol) {
+        match start {
+            Symbol::Nonterminal(index) => self.start = index,
+            _ => panic!("start symbol must be a nonterminal"),
+        }
+    }
+
+    /// Add `lhs ::= rhs` and return its production index.
+    pub fn add_production(&mut self, lhs: Symbol, rhs: Vec<Symbol>) -> usize {
+        let lhs = match lhs {
+            Symbol::Nonterminal(index) => index,
+            _ => panic!("lhs must be a nonterminal"),
+        };
+        assert!(!rhs.contains(&Symbol::Eps), "use an empty rhs for eps");
+        self.productions.push(Production { lhs, rhs });
+        self.productions.len() - 1
+    }
+
+    /// Declare the priority and associativity of a production.
+    pub fn set_priority(&mut self, production: usize, level: usize, assoc: Assoc) {
+        self.priorities
+            .insert(production, Priority { level, assoc });
+    }
+
+    /// Mark a production as rejected, preferred or avoided.
+    pub fn set_mark(&mut self, production: usize, mark: Mark) {
+        self.marks.insert(production, mark);
+    }
+
+    /// Production indices of each nonterminal, in declaration order.
+    pub fn alternatives(&self) -> Vec<Vec<usize>> {
+        let mut alternatives = vec![vec![]; self.nonterminals.len()];
+        for (index, production) in self.productions.iter().enumerate() {
+            alternatives[production.lhs].push(index);
+        }
+        alternatives
+    }
+
+    pub fn symbol_name(&self, symbol: Symbol) -> &str {
+        match symbol {
+            Symbol::Terminal(index) => &self.terminals[index],
+            Symbol::Nonterminal(index) => &self.nonterminals[index],
+            Symbol::Eps => "eps",
+        }
+    }
+
+    pub fn display_slot(&self, slot: Slot) -> DisplaySlot<'_> {
+        DisplaySlot {
+            grammar: self,
+            slot,
+        }
+    }
+}
+
+/// Formats a slot as `X ::= α . β`.
+pub struct DisplaySlot<'a> {
+    grammar: &'a Grammar,
+    slot: Slot,
+}
+
+impl<'a> fmt::Display for DisplaySlot<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let production = &self.grammar.productions[self.slot.production];
+        write!(f, "{} ::=", self.grammar.nonterminals[production.lhs])?;
+        for (index, symbol) in production.rhs.iter().enumerate() {
+            if index == self.slot.dot {
+                write!(f, " .")?;
+            }
+            write!(f, " {}", self.grammar.symbol_name(*symbol))?;
+        }
+        if self.slot.dot == production.rhs.len() {
+            write!(f, " .")?;
+        }
+        Ok(())
+    }
+}
+
+// SS -> S $
+// S -> A S d | B S | eps
+// A -> a | c
+// B -> a | b
+/// Γ1 from the GLL paper.
+pub fn gamma1() -> Grammar {
+    let mut grammar = Grammar::new();
+    let s = grammar.nonterminal("S");
+    let a = grammar.nonterminal("A");
+    let b = grammar.nonterminal("B");
+    let ta = grammar.terminal("a");
+    let tb = grammar.terminal("b");
+    let tc = grammar.terminal("c");
+    let td = grammar.terminal("d");
+    grammar.add_production(s, vec![a, s, td]);
+    grammar.add_production(s, vec![b, s]);
+    grammar.add_production(s, vec![]);
+    grammar.add_production(a, vec![ta]);
+    grammar.add_production(a, vec![tc]);
+    grammar.add_production(b, vec![ta]);
+    grammar.add_production(b, vec![tb]);
+    grammar.set_start(s);
+    grammar
+}
@@ -1,563 +1,1567 @@
-use petgraph::dot::Dot;
+use crate::analysis::{token_lookahead, Analysis, Lookahead};
+use crate::grammar::{gamma1, Grammar, Slot, Symbol};
+use crate::input::{Input, Token};
+use crate::observer::ParseObserver;
+use crate::outcome::{Diagnostic, Furthest, ParseError, ParseOutcome};
+use crate::scheduler::{Pending, Scheduler};
+use crate::sppf::{NodeId, NodeKind, SPPFNode, SPPFNodeIndex, Sppf, DUMMY};
+use crate::statistics::Statistics;
 use petgraph::{
     graph::{EdgeReference, NodeIndex},
     visit::EdgeRef,
     Directed, Graph,
 };
-use std::collections::{BTreeMap, BTreeSet};
-use std::fmt::Debug;
-use std::fs::File;
-use std::io::Write;
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
-enum Label {
-    Ret,
-    L0,
-    LS,
-    LS_0,   // S -> . A S d
-    LS_0_1, // S -> A . S d
-    LS_0_2, // S -> A S . d
-    L3,     // S -> A S d .
-    LS_1,   // S -> . B S
-    LS_1_1, // S -> B . S
-    LS_1_2, // S -> B S .
-    LS_2,   // S -> .
-    LA,
-    LA_3, // A -> . a
-    L6,   // A -> a .
-    LA_4, // A -> . c
-    L7,   // A -> c .
-    LB,
-    LB_5,   // B -> . a
-    LB_5_1, // B -> a .
-    LB_6,   // B -> . b
-    L9,     // B -> b .
-}
-
-type GSSNode<L> = (L, usize);
-
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-enum Symbol {
-    // terminals
-    TA,
-    TB,
-    TC,
-    TD,
-    // non terminals
-    NS,
-    NA,
-    NB,
-    // eps
-    Eps,
-}
-
-type SPPFNodeIndex = usize;
-
-trait GrammarSymbol {
-    fn is_eps(&self) -> bool;
-}
-
-impl GrammarSymbol for Symbol {
-    fn is_eps(&self) -> bool {
-        *self == Symbol::Eps
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-enum SPPFNode<L, S> {
-    Dummy,
-    // usize, usize: from, to
-    // Vec<SPPFNodeIndex>: children
-    Symbol(S, usize, usize, Vec<SPPFNodeIndex>),
-    Intermediate(L, usize, usize, Vec<SPPFNodeIndex>),
-    Packed(L, usize, Vec<SPPFNodeIndex>),
-}
-
-trait GrammarLabel {
-    type Symbol: PartialEq + GrammarSymbol;
-    fn first(&self) -> bool;
-    // return Some(lhs) if it is the end
-    fn end(&self) -> Option<Self::Symbol>;
+use sealed::{Build, Nodes};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{self, Read};
+use std::mem::size_of;
+use std::time::Instant;
+
+type GSSNode = (Slot, usize);
+type Descriptor = (Slot, NodeIndex, usize, SPPFNodeIndex);
+// kind and extent of the SPPF node on an edge, None for the dummy node
+type GSSEdge = Option<(NodeKind, (usize, usize))>;
+
+/// Label of the initial GSS node, not a slot of any production.
+pub(crate) const L0: Slot = Slot {
+    production: usize::MAX,
+    dot: 0,
+};
+
+/// Graph structured stack left behind by a parse.
+///
+/// Nodes are (return slot, input position) pairs; each edge carries the
+/// SPPF node built so far.
+#[derive(Debug, Clone)]
+pub struct Gss {
+    pub(crate) graph: Graph<GSSNode, GSSEdge, Directed>,
+}
+
+struct GSSState<'g, 'o, O: ParseObserver, F: Nodes> {
+    grammar: &'g Grammar,
+    observer: &'o mut O,
+    analysis: Analysis,
+    alternatives: Vec<Vec<usize>>,
+    graph: Graph<GSSNode, SPPFNodeIndex, Directed>,
+    nodes: BTreeMap<GSSNode, NodeIndex>,
+    forest: F,
+    initial_node_index: NodeIndex,
+    // right extents of the derivations of the start symbol from 0
+    ends: BTreeSet<usize>,
+    furthest: Furthest,
+    // whether to recover from errors, keeping the descriptors that failed
+    recover: bool,
+    stuck: Vec<Descriptor>,
+    // position where recovery lets nullable nonterminals derive eps
+    // whatever the lookahead
+    recovering: Option<usize>,
+    statistics: Statistics,
+    visited: Visited,                                 // U_j
+    todo: Pending<Descriptor>,                        // R
+    pop: HashMap<NodeIndex, BTreeSet<SPPFNodeIndex>>, // P
+    current_position: usize,                          // C_i
+    current_node_index: NodeIndex,                    // C_u
+    current_sppf_node: usize,                         // C_n
+}
+
+// the sets U_j, made as positions are reached and freed once passed
+#[derive(Default)]
+struct Visited {
+    // position of the first set, those before having been freed
+    first: usize,
+    sets: VecDeque<HashSet<(Slot, NodeIndex, SPPFNodeIndex)>>,
+    // number of descriptors in the sets
+    entries: usize,
 }
 
-impl GrammarLabel for Label {
-    type Symbol = Symbol;
-    fn first(&self) -> bool {
-        use Label::*;
-        [LS_0_1, LS_1_1].contains(self)
+impl Visited {
+    fn insert(&mut self, i: usize, descriptor: (Slot, NodeIndex, SPPFNodeIndex)) -> bool {
+        let index = i - self.first;
+        if index >= self.sets.len() {
+            self.sets.resize_with(index + 1, HashSet::new);
+        }
+        let inserted = self.sets[index].insert(descriptor);
+        if inserted {
+            self.entries += 1;
+        }
+        inserted
     }
 
-    fn end(&self) -> Option<Symbol> {
-        use Label::*;
-        use Symbol::*;
-        match self {
-            L3 => Some(NS),
-            LS_1_2 => Some(NS),
-            LS_2 => Some(NS),
-            L6 => Some(NA),
-            L7 => Some(NA),
-            LB_5_1 => Some(NB),
-            L9 => Some(NB),
-            _ => None,
+    // free the sets before position j
+    fn free(&mut self, j: usize) {
+        while self.first < j {
+            if let Some(set) = self.sets.pop_front() {
+                self.entries -= set.len();
+            }
+            self.first += 1;
         }
     }
 }
 
-impl<L, S> SPPFNode<L, S> {
-    fn right_extent(&self) -> usize {
-        use SPPFNode::*;
-        match self {
-            Symbol(_, _, r, _) => *r,
-            Intermediate(_, _, r, _) => *r,
-            _ => unimplemented!(),
+mod sealed {
+    use crate::grammar::{Grammar, Slot, Symbol};
+    use crate::observer::ParseObserver;
+    use crate::outcome::ParseOutcome;
+    use crate::sppf::NodeId;
+    use crate::statistics::Statistics;
+
+    // what descriptors, GSS edges and pops carry: an SPPF node when parsing,
+    // only its right extent when recognizing, plus one so that it differs
+    // from the dummy node
+    pub trait Nodes {
+        // node for the terminal or eps x over input[i..j]
+        fn leaf<O: ParseObserver>(
+            &mut self,
+            observer: &mut O,
+            x: Symbol,
+            i: usize,
+            j: usize,
+        ) -> usize;
+
+        // node for x, not matched at i, skipping input up to j
+        fn error<O: ParseObserver>(
+            &mut self,
+            observer: &mut O,
+            x: Symbol,
+            i: usize,
+            j: usize,
+        ) -> usize;
+
+        // getNodeP(l, w, z) past the first(l) shortcut, t being Some(lhs) if
+        // l is at the end
+        fn packed<O: ParseObserver>(
+            &mut self,
+            observer: &mut O,
+            l: Slot,
+            t: Option<Symbol>,
+            w: usize,
+            z: usize,
+        ) -> usize;
+
+        fn right_extent(&self, z: usize) -> usize;
+
+        // what observers see for w, None for the dummy node
+        fn observed(&self, w: usize) -> Option<NodeId>;
+    }
+
+    pub trait Build<T>: Nodes {
+        type Output;
+
+        // the result from the derivation of the start symbol over
+        // input[..end], if any, None if it was filtered out; errors tells
+        // whether error nodes were made
+        fn output(
+            self,
+            grammar: &Grammar,
+            input: &[T],
+            end: Option<usize>,
+            errors: bool,
+            statistics: &mut Statistics,
+        ) -> Option<ParseOutcome<Self::Output>>;
+    }
+}
+
+/// What the main loop builds as it goes, see `parse_configured`:
+/// `SppfBuilder` builds the SPPF, `Recognizer` nothing but the answer.
+pub trait Forest<T>: Build<T> {}
+
+impl<T, F: Build<T>> Forest<T> for F {}
+
+/// Builds the SPPF of the input, as `parse_with` does.
+///
+/// Nodes are found by key; with the `linear-scan` feature they are found
+/// by walking all of them instead, the lookup this replaced.
+#[derive(Debug, Clone)]
+pub struct SppfBuilder {
+    sppf_nodes: Vec<SPPFNode>,
+    symbol_nodes: HashMap<(Symbol, usize, usize), SPPFNodeIndex>,
+    intermediate_nodes: HashMap<(Slot, usize, usize), SPPFNodeIndex>,
+    error_nodes: HashMap<(Symbol, usize, usize), SPPFNodeIndex>,
+    packed_nodes: HashSet<(SPPFNodeIndex, Slot, usize)>,
+}
+
+impl Default for SppfBuilder {
+    fn default() -> SppfBuilder {
+        SppfBuilder {
+            sppf_nodes: vec![SPPFNode::Dummy],
+            symbol_nodes: HashMap::new(),
+            intermediate_nodes: HashMap::new(),
+            error_nodes: HashMap::new(),
+            packed_nodes: HashSet::new(),
         }
     }
+}
 
-    fn left_extent(&self) -> usize {
-        use SPPFNode::*;
-        match self {
-            Symbol(_, l, _, _) => *l,
-            Intermediate(_, l, _, _) => *l,
-            _ => unimplemented!(),
+impl SppfBuilder {
+    // the GSS, with the kind and extent of the SPPF node on each edge
+    fn gss(&self, graph: &Graph<GSSNode, SPPFNodeIndex, Directed>) -> Gss {
+        Gss {
+            graph: graph.map(
+                |_, node| *node,
+                |_, &w| {
+                    let node = &self.sppf_nodes[w];
+                    node.extent().map(|extent| (node.kind(), extent))
+                },
+            ),
         }
     }
 
-    fn children(&self) -> Option<&Vec<SPPFNodeIndex>> {
-        use SPPFNode::*;
-        match self {
-            Dummy => None,
-            Symbol(_, _, _, children) => Some(children),
-            Intermediate(_, _, _, children) => Some(children),
-            Packed(_, _, children) => Some(children),
+    // count the nodes by kind, and their bytes
+    fn count(&self, statistics: &mut Statistics) {
+        let mut bytes = self.sppf_nodes.len() * size_of::<SPPFNode>();
+        for node in &self.sppf_nodes {
+            match node {
+                SPPFNode::Symbol(_, _, _, _) => statistics.symbol_nodes += 1,
+                SPPFNode::Intermediate(_, _, _, _) => statistics.intermediate_nodes += 1,
+                SPPFNode::Packed(_, _, _) => statistics.packed_nodes += 1,
+                SPPFNode::Dummy | SPPFNode::Error(_, _, _) => {}
+            }
+            bytes += node.children().map_or(0, Vec::len) * size_of::<SPPFNodeIndex>();
         }
+        let entries =
+            self.symbol_nodes.len() + self.intermediate_nodes.len() + self.error_nodes.len();
+        bytes += entries * size_of::<((Symbol, usize, usize), SPPFNodeIndex)>();
+        bytes += self.packed_nodes.len() * size_of::<(SPPFNodeIndex, Slot, usize)>();
+        statistics.memory.sppf = bytes;
     }
 
-    fn children_mut(&mut self) -> Option<&mut Vec<SPPFNodeIndex>> {
-        use SPPFNode::*;
-        match self {
-            Symbol(_, _, _, children) => Some(children),
-            Intermediate(_, _, _, children) => Some(children),
-            _ => unimplemented!(),
+    fn find_or_create_sppf_symbol<O: ParseObserver>(
+        &mut self,
+        observer: &mut O,
+        s: Symbol,
+        i: usize,
+        j: usize,
+    ) -> SPPFNodeIndex {
+        if let Some(index) = self.symbol_node(s, i, j) {
+            return index;
+        }
+        self.sppf_nodes.push(SPPFNode::Symbol(s, i, j, vec![]));
+        let index = self.sppf_nodes.len() - 1;
+        if !cfg!(feature = "linear-scan") {
+            self.symbol_nodes.insert((s, i, j), index);
         }
+        observer.sppf_node_created(NodeId(index), NodeKind::Symbol(s), (i, j));
+        index
+    }
+
+    fn find_or_create_sppf_intermediate<O: ParseObserver>(
+        &mut self,
+        observer: &mut O,
+        l: Slot,
+        i: usize,
+        j: usize,
+    ) -> SPPFNodeIndex {
+        let found = if cfg!(feature = "linear-scan") {
+            self.scan_intermediate(l, i, j)
+        } else {
+            self.intermediate_nodes.get(&(l, i, j)).copied()
+        };
+        if let Some(index) = found {
+            return index;
+        }
+        self.sppf_nodes
+            .push(SPPFNode::Intermediate(l, i, j, vec![]));
+        let index = self.sppf_nodes.len() - 1;
+        if !cfg!(feature = "linear-scan") {
+            self.intermediate_nodes.insert((l, i, j), index);
+        }
+        observer.sppf_node_created(NodeId(index), NodeKind::Intermediate(l), (i, j));
+        index
+    }
+
+    // the symbol node (s, i, j), if made
+    fn symbol_node(&self, s: Symbol, i: usize, j: usize) -> Option<SPPFNodeIndex> {
+        if cfg!(feature = "linear-scan") {
+            self.scan_symbol(s, i, j)
+        } else {
+            self.symbol_nodes.get(&(s, i, j)).copied()
+        }
+    }
+
+    // With the linear-scan feature, nodes are found by walking them, as
+    // before the keyed indices, so that benches/ambiguous.rs can compare.
+
+    fn scan_symbol(&self, s: Symbol, i: usize, j: usize) -> Option<SPPFNodeIndex> {
+        self.sppf_nodes.iter().position(|node| match node {
+            SPPFNode::Symbol(node_s, node_i, node_j, _) => {
+                *node_s == s && *node_i == i && *node_j == j
+            }
+            _ => false,
+        })
+    }
+
+    fn scan_intermediate(&self, l: Slot, i: usize, j: usize) -> Option<SPPFNodeIndex> {
+        self.sppf_nodes.iter().position(|node| match node {
+            SPPFNode::Intermediate(node_l, node_i, node_j, _) => {
+                *node_l == l && *node_i == i && *node_j == j
+            }
+            _ => false,
+        })
+    }
+
+    fn scan_error(&self, x: Symbol, i: usize, j: usize) -> Option<SPPFNodeIndex> {
+        self.sppf_nodes.iter().position(|node| match node {
+            SPPFNode::Error(node_x, node_i, node_j) => *node_x == x && *node_i == i && *node_j == j,
+            _ => false,
+        })
+    }
+
+    // whether y has a packed child (l, k)
+    fn scan_packed(&self, y: SPPFNodeIndex, l: Slot, k: usize) -> bool {
+        let children = self.sppf_nodes[y].children().unwrap();
+        children.iter().any(|index| match &self.sppf_nodes[*index] {
+            SPPFNode::Packed(node_l, node_k, _) => *node_l == l && *node_k == k,
+            _ => false,
+        })
     }
 }
 
-#[derive(Debug)]
-struct GSSState<L: Ord + Clone + GrammarLabel> {
-    graph: Graph<GSSNode<L>, SPPFNodeIndex, Directed>,
-    nodes: BTreeMap<GSSNode<L>, NodeIndex>,
-    sppf_nodes: Vec<SPPFNode<L, L::Symbol>>,
-    initial_node_index: NodeIndex,
-    visited: Vec<BTreeSet<(L, NodeIndex, SPPFNodeIndex)>>, // U_j
-    todo: Vec<(L, NodeIndex, usize, SPPFNodeIndex)>,       // R
-    pop: BTreeSet<(NodeIndex, SPPFNodeIndex)>,             // P
-    current_position: usize,                               // C_i
-    current_node_index: NodeIndex,                         // C_u
-    current_sppf_node: usize,                              // C_n
+impl Nodes for SppfBuilder {
+    fn leaf<O: ParseObserver>(
+        &mut self,
+        observer: &mut O,
+        x: Symbol,
+        i: usize,
+        j: usize,
+    ) -> SPPFNodeIndex {
+        self.find_or_create_sppf_symbol(observer, x, i, j)
+    }
+
+    fn error<O: ParseObserver>(
+        &mut self,
+        observer: &mut O,
+        x: Symbol,
+        i: usize,
+        j: usize,
+    ) -> SPPFNodeIndex {
+        let found = if cfg!(feature = "linear-scan") {
+            self.scan_error(x, i, j)
+        } else {
+            self.error_nodes.get(&(x, i, j)).copied()
+        };
+        if let Some(index) = found {
+            return index;
+        }
+        self.sppf_nodes.push(SPPFNode::Error(x, i, j));
+        let index = self.sppf_nodes.len() - 1;
+        if !cfg!(feature = "linear-scan") {
+            self.error_nodes.insert((x, i, j), index);
+        }
+        observer.sppf_node_created(NodeId(index), NodeKind::Error(x), (i, j));
+        index
+    }
+
+    fn packed<O: ParseObserver>(
+        &mut self,
+        observer: &mut O,
+        l: Slot,
+        t: Option<Symbol>,
+        w: SPPFNodeIndex,
+        z: SPPFNodeIndex,
+    ) -> SPPFNodeIndex {
+        let (k, i) = self.sppf_nodes[z].extent().unwrap();
+        let (j, packed_children) = if w != DUMMY {
+            // w != $
+            let (j, pivot) = self.sppf_nodes[w].extent().unwrap();
+            assert_eq!(pivot, k);
+            (j, vec![w, z])
+        } else {
+            // w = $
+            (k, vec![z])
+        };
+        let y = if let Some(t) = t {
+            // t = X
+            self.find_or_create_sppf_symbol(observer, t, j, i)
+        } else {
+            // t = l
+            self.find_or_create_sppf_intermediate(observer, l, j, i)
+        };
+        let new = if cfg!(feature = "linear-scan") {
+            !self.scan_packed(y, l, k)
+        } else {
+            self.packed_nodes.insert((y, l, k))
+        };
+        if new {
+            let len = self.sppf_nodes.len();
+            self.sppf_nodes[y].children_mut().unwrap().push(len);
+            self.sppf_nodes
+                .push(SPPFNode::Packed(l, k, packed_children));
+            observer.sppf_node_created(NodeId(len), NodeKind::Packed(l, k), (j, i));
+        }
+        y
+    }
+
+    fn right_extent(&self, z: SPPFNodeIndex) -> usize {
+        self.sppf_nodes[z].extent().unwrap().1
+    }
+
+    fn observed(&self, w: SPPFNodeIndex) -> Option<NodeId> {
+        if w == DUMMY {
+            None
+        } else {
+            Some(NodeId(w))
+        }
+    }
+}
+
+impl<T: Token + Clone> Build<T> for SppfBuilder {
+    type Output = Sppf<T>;
+
+    fn output(
+        self,
+        grammar: &Grammar,
+        input: &[T],
+        end: Option<usize>,
+        errors: bool,
+        statistics: &mut Statistics,
+    ) -> Option<ParseOutcome<Sppf<T>>> {
+        self.count(statistics);
+        let start = Symbol::Nonterminal(grammar.start);
+        let root = end.map(|end| self.symbol_node(start, 0, end).unwrap());
+        let mut sppf = Sppf {
+            nodes: self.sppf_nodes,
+            root,
+            tokens: input.to_vec(),
+        };
+        if !grammar.priorities.is_empty() {
+            sppf = sppf.filter_priorities(grammar);
+        }
+        if !grammar.marks.is_empty() {
+            sppf = sppf.filter_marks(grammar);
+        }
+        if errors {
+            sppf = sppf.filter_errors();
+        }
+        let root = sppf.root?;
+        Some(ParseOutcome {
+            end: sppf.extent(NodeId(root)).1,
+            value: sppf,
+        })
+    }
+}
+
+/// Builds nothing, so that a parse only tells whether the input is a
+/// sentence: descriptors and GSS edges keep the right extent of the SPPF
+/// node they would carry, a position, and observers see that position as
+/// the `NodeId`, `None` still standing for the dummy node.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Recognizer;
+
+impl<T> Build<T> for Recognizer {
+    type Output = ();
+
+    fn output(
+        self,
+        _: &Grammar,
+        _: &[T],
+        end: Option<usize>,
+        _: bool,
+        _: &mut Statistics,
+    ) -> Option<ParseOutcome<()>> {
+        end.map(|end| ParseOutcome { value: (), end })
+    }
+}
+
+impl Nodes for Recognizer {
+    fn leaf<O: ParseObserver>(&mut self, _: &mut O, _: Symbol, _: usize, j: usize) -> usize {
+        j + 1
+    }
+
+    fn error<O: ParseObserver>(&mut self, _: &mut O, _: Symbol, _: usize, j: usize) -> usize {
+        j + 1
+    }
+
+    fn packed<O: ParseObserver>(
+        &mut self,
+        _: &mut O,
+        _: Slot,
+        _: Option<Symbol>,
+        _: usize,
+        z: usize,
+    ) -> usize {
+        z
+    }
+
+    fn right_extent(&self, z: usize) -> usize {
+        z - 1
+    }
+
+    fn observed(&self, w: usize) -> Option<NodeId> {
+        w.checked_sub(1).map(NodeId)
+    }
 }
 
-impl<L: Ord + Clone + GrammarLabel> GSSState<L> {
-    fn add(&mut self, l: L, u: NodeIndex, i: usize, w: SPPFNodeIndex) {
-        if !self.visited[i].contains(&(l.clone(), u, w)) {
-            self.visited[i].insert((l.clone(), u, w));
-            self.todo.push((l, u, i, w));
+impl<'g, 'o, O: ParseObserver, F: Nodes> GSSState<'g, 'o, O, F> {
+    fn new(
+        grammar: &'g Grammar,
+        observer: &'o mut O,
+        forest: F,
+        scheduler: Scheduler,
+        recover: bool,
+    ) -> GSSState<'g, 'o, O, F> {
+        let mut graph: Graph<GSSNode, SPPFNodeIndex, Directed> = Graph::new();
+        let mut nodes = BTreeMap::new();
+        let initial_node = (L0, 0);
+        let initial_node_index = graph.add_node(initial_node);
+        nodes.insert(initial_node, initial_node_index);
+        observer.gss_node_created(initial_node_index.index(), None, 0);
+
+        GSSState {
+            grammar,
+            observer,
+            analysis: Analysis::new(grammar),
+            alternatives: grammar.alternatives(),
+            graph,
+            nodes,
+            forest,
+            initial_node_index,
+            ends: BTreeSet::new(),
+            furthest: Furthest::default(),
+            recover,
+            stuck: vec![],
+            recovering: None,
+            statistics: Statistics {
+                gss_nodes: 1,
+                ..Statistics::default()
+            },
+            visited: Visited::default(),
+            todo: Pending::new(scheduler),
+            pop: HashMap::new(),
+            current_node_index: initial_node_index,
+            current_sppf_node: DUMMY,
+            current_position: 0,
+        }
+    }
+
+    fn start(&mut self, lookahead: Option<Lookahead>) {
+        self.call(self.grammar.start, self.initial_node_index, 0, lookahead);
+    }
+
+    // add (X ::= . α, u, i, $) if test(I[i], X, α), for each X ::= α
+    fn call(&mut self, x: usize, u: NodeIndex, i: usize, lookahead: Option<Lookahead>) {
+        for index in 0..self.alternatives[x].len() {
+            let l = Slot {
+                production: self.alternatives[x][index],
+                dot: 0,
+            };
+            if self.test(lookahead, l, i) {
+                self.add(l, u, i, DUMMY);
+            } else {
+                self.fail((l, u, i, DUMMY));
+            }
+        }
+    }
+
+    // test(I[i], X, β) at X ::= α . β; where recovery is under way, β may
+    // also start with a nullable nonterminal or be eps, to derive eps
+    fn test(&self, lookahead: Option<Lookahead>, l: Slot, i: usize) -> bool {
+        if self.analysis.test(lookahead, l) {
+            return true;
+        }
+        let rhs = &self.grammar.productions[l.production].rhs;
+        self.recovering == Some(i)
+            && match rhs.get(l.dot) {
+                None => true,
+                Some(&Symbol::Nonterminal(y)) => self.analysis.nullable[y],
+                Some(_) => false,
+            }
+    }
+
+    // record that none of the lookaheads selected at the slot of a
+    // descriptor matched at its position, keeping it if recovering
+    fn fail(&mut self, descriptor: Descriptor) {
+        let (l, _, i, _) = descriptor;
+        let grammar = self.grammar;
+        let production = &grammar.productions[l.production];
+        let expected = self.analysis.select(l).iter().map(|x| x.name(grammar));
+        let name = &grammar.nonterminals[production.lhs];
+        self.furthest.fail(i, expected, name);
+        if self.recover && l.dot < production.rhs.len() {
+            self.stuck.push(descriptor);
+        }
+    }
+
+    // run a descriptor taken from R, lookahead giving the terminal at a position
+    fn process(
+        &mut self,
+        (l, u, i, w): Descriptor,
+        lookahead: impl Fn(usize) -> Option<Lookahead>,
+    ) {
+        self.observer
+            .descriptor_dequeued(l, u.index(), i, self.forest.observed(w));
+        self.statistics.descriptors_processed += 1;
+        let grammar = self.grammar;
+        let mut current_label = l;
+        self.current_node_index = u;
+        self.current_position = i;
+        self.current_sppf_node = w;
+        loop {
+            let rhs = &grammar.productions[current_label.production].rhs;
+            if current_label.dot == rhs.len() {
+                if rhs.is_empty() {
+                    // X ::= .
+                    let right = self.get_node_t(Symbol::Eps, self.current_position);
+                    self.current_sppf_node =
+                        self.get_node_p(current_label, self.current_sppf_node, right);
+                }
+                self.pop(
+                    self.current_node_index,
+                    self.current_position,
+                    self.current_sppf_node,
+                );
+                break;
+            }
+            let next_label = Slot {
+                production: current_label.production,
+                dot: current_label.dot + 1,
+            };
+            let current = (
+                current_label,
+                self.current_node_index,
+                self.current_position,
+                self.current_sppf_node,
+            );
+            match rhs[current_label.dot] {
+                Symbol::Terminal(t) => {
+                    // X ::= α . a β
+                    if lookahead(self.current_position) != Some(Lookahead::Terminal(t)) {
+                        self.fail(current);
+                        break;
+                    }
+                    let right = self.get_node_t(Symbol::Terminal(t), self.current_position);
+                    self.current_position += 1;
+                    self.current_sppf_node =
+                        self.get_node_p(next_label, self.current_sppf_node, right);
+                    current_label = next_label;
+                }
+                Symbol::Nonterminal(x) => {
+                    // X ::= α . Y β
+                    let x_lookahead = lookahead(self.current_position);
+                    if !self.test(x_lookahead, current_label, self.current_position) {
+                        self.fail(current);
+                        break;
+                    }
+                    self.current_node_index = self.create(
+                        next_label,
+                        self.current_node_index,
+                        self.current_position,
+                        self.current_sppf_node,
+                    );
+                    self.call(
+                        x,
+                        self.current_node_index,
+                        self.current_position,
+                        x_lookahead,
+                    );
+                    break;
+                }
+                Symbol::Eps => unreachable!(),
+            }
+        }
+    }
+
+    // whether the start symbol derives the input up to its end m; if not,
+    // a derivation stopping short of m expected the end of input there
+    fn accepts(&mut self, m: usize) -> bool {
+        if self.ends.contains(&m) {
+            return true;
+        }
+        if let Some(&right) = self.ends.iter().next_back() {
+            let grammar = self.grammar;
+            let name = &grammar.nonterminals[grammar.start];
+            self.furthest
+                .fail(right, Some(Lookahead::Eof.name(grammar)), name);
+        }
+        false
+    }
+
+    // forget what is kept for the positions before j: the sets U_i, and
+    // the ends of the start symbol but the last
+    fn free(&mut self, j: usize) {
+        self.visited.free(j);
+        let ends = self.ends.split_off(&j);
+        let last = self.ends.iter().next_back().cloned();
+        self.ends = ends;
+        self.ends.extend(last);
+    }
+
+    fn finish(self, end: Option<usize>, diagnostics: Vec<Diagnostic>) -> Run<F> {
+        let mut statistics = self.statistics;
+        statistics.memory.gss = statistics.gss_nodes * size_of::<(GSSNode, (GSSNode, NodeIndex))>()
+            + statistics.gss_edges * size_of::<(NodeIndex, NodeIndex, SPPFNodeIndex)>();
+        Run {
+            forest: self.forest,
+            graph: self.graph,
+            end,
+            furthest: self.furthest,
+            diagnostics,
+            statistics,
+        }
+    }
+
+    // X ::= α . β where α is a terminal or a non-nullable nonterminal and β != eps
+    fn first(&self, l: Slot) -> bool {
+        let rhs = &self.grammar.productions[l.production].rhs;
+        l.dot == 1
+            && rhs.len() > 1
+            && match rhs[0] {
+                Symbol::Terminal(_) => true,
+                Symbol::Nonterminal(x) => !self.analysis.nullable[x],
+                Symbol::Eps => false,
+            }
+    }
+
+    // return Some(lhs) if it is the end
+    fn end(&self, l: Slot) -> Option<Symbol> {
+        let production = &self.grammar.productions[l.production];
+        if l.dot == production.rhs.len() {
+            Some(Symbol::Nonterminal(production.lhs))
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, l: Slot, u: NodeIndex, i: usize, w: SPPFNodeIndex) {
+        if self.visited.insert(i, (l, u, w)) {
+            self.observer
+                .descriptor_added(l, u.index(), i, self.forest.observed(w));
+            self.todo.push(i, (l, u, i, w));
+            let statistics = &mut self.statistics;
+            statistics.descriptors_added += 1;
+            let visited = self.visited.entries * size_of::<(Slot, NodeIndex, SPPFNodeIndex)>();
+            statistics.memory.visited = statistics.memory.visited.max(visited);
+            let todo = self.todo.len() * size_of::<Descriptor>();
+            statistics.memory.todo = statistics.memory.todo.max(todo);
+        } else {
+            self.statistics.duplicates += 1;
         }
     }
 
     fn pop(&mut self, u: NodeIndex, i: usize, z: SPPFNodeIndex) {
-        if u != self.initial_node_index {
-            let (l, _k) = self.graph[u].clone();
-            self.pop.insert((u, z));
+        let popped = self.forest.observed(z).unwrap();
+        self.observer.pop(u.index(), i, popped);
+        self.statistics.pops += 1;
+        if u == self.initial_node_index {
+            self.ends.insert(i);
+        } else {
+            let (l, _k) = self.graph[u];
+            if self.pop.entry(u).or_default().insert(z) {
+                self.statistics.memory.popped += size_of::<SPPFNodeIndex>();
+            }
             let edges: Vec<EdgeReference<SPPFNodeIndex>> = self.graph.edges(u).collect();
             let edge_data: Vec<(NodeIndex, SPPFNodeIndex)> = edges
                 .iter()
                 .map(|edge| (edge.target(), *edge.weight()))
                 .collect();
             for (v, w) in edge_data {
-                let y = self.get_node_p(l.clone(), w, z);
-                self.add(l.clone(), v, i, y);
+                let y = self.get_node_p(l, w, z);
+                self.add(l, v, i, y);
             }
         }
     }
 
-    fn create(&mut self, l: L, u: NodeIndex, j: usize, w: SPPFNodeIndex) -> NodeIndex {
-        let node = (l.clone(), j);
+    fn create(&mut self, l: Slot, u: NodeIndex, j: usize, w: SPPFNodeIndex) -> NodeIndex {
+        let node = (l, j);
         let v = if let Some(index) = self.nodes.get(&node) {
             *index
         } else {
-            let index = self.graph.add_node(node.clone());
+            let index = self.graph.add_node(node);
             self.nodes.insert(node, index);
+            self.observer.gss_node_created(index.index(), Some(l), j);
+            self.statistics.gss_nodes += 1;
             index
         };
         if self.graph.find_edge(v, u).is_none() {
             self.graph.add_edge(v, u, w);
-            let pop = self.pop.clone();
-            for (index, z) in pop.into_iter() {
-                if index == v {
-                    let y = self.get_node_p(l.clone(), w, z);
-                    let h = self.sppf_nodes[z].right_extent();
-                    self.add(l.clone(), u, h, y);
-                }
+            self.observer
+                .gss_edge_created(v.index(), u.index(), self.forest.observed(w));
+            self.statistics.gss_edges += 1;
+            let pop = self.pop.get(&v).cloned().unwrap_or_default();
+            for z in pop {
+                let y = self.get_node_p(l, w, z);
+                let h = self.forest.right_extent(z);
+                self.add(l, u, h, y);
             }
         }
         v
     }
 
-    fn get_node_t(&mut self, x: L::Symbol, i: usize) -> SPPFNodeIndex {
-        let h = if x.is_eps() { i } else { i + 1 };
-        self.find_or_create_sppf_symbol(x, i, h)
+    fn get_node_t(&mut self, x: Symbol, i: usize) -> SPPFNodeIndex {
+        let h = if x == Symbol::Eps { i } else { i + 1 };
+        self.forest.leaf(&mut *self.observer, x, i, h)
     }
 
-    fn get_node_p(&mut self, l: L, w: SPPFNodeIndex, z: SPPFNodeIndex) -> SPPFNodeIndex {
-        if l.first() {
+    fn get_node_p(&mut self, l: Slot, w: SPPFNodeIndex, z: SPPFNodeIndex) -> SPPFNodeIndex {
+        if self.first(l) {
             return z;
-        } else {
-            let node_z = &self.sppf_nodes[z];
-            let k = node_z.left_extent();
-            let i = node_z.right_extent();
-            let node_w = &self.sppf_nodes[w];
-            if SPPFNode::Dummy != *node_w {
-                // w != $
-                let j = node_w.left_extent();
-                assert_eq!(node_w.right_extent(), k);
-                if let Some(t) = l.end() {
-                    // t = X
-                    let y = self.find_or_create_sppf_symbol(t, j, i);
-                    if let Some(children) = self.sppf_nodes[y].children() {
-                        if !children.iter().any(|index| match &self.sppf_nodes[*index] {
-                            SPPFNode::Packed(node_l, node_k, _) => *node_l == l && *node_k == k,
-                            _ => false,
-                        }) {
-                            let len = self.sppf_nodes.len();
-                            self.sppf_nodes[y].children_mut().unwrap().push(len);
-                            self.sppf_nodes.push(SPPFNode::Packed(l, k, vec![w, z]));
-                        }
-                    } else {
-                        unimplemented!()
-                    }
-                    y
-                } else {
-                    // t = l
-                    let y = self.find_or_create_sppf_intermediate(l.clone(), j, i);
-                    if let Some(children) = self.sppf_nodes[y].children() {
-                        if !children.iter().any(|index| match &self.sppf_nodes[*index] {
-                            SPPFNode::Packed(node_l, node_k, _) => *node_l == l && *node_k == k,
-                            _ => false,
-                        }) {
-                            let len = self.sppf_nodes.len();
-                            self.sppf_nodes[y].children_mut().unwrap().push(len);
-                            self.sppf_nodes.push(SPPFNode::Packed(l, k, vec![w, z]));
-                        }
-                    } else {
-                        unimplemented!()
-                    }
-                    y
+        }
+        let t = self.end(l);
+        self.forest.packed(&mut *self.observer, l, t, w, z)
+    }
+
+    // go on from a descriptor stuck at X ::= α . x β as if x matched
+    // input[i..j], lookahead being I[i]. A nullable nonterminal derives eps
+    // if nothing is skipped; otherwise an error node for x is popped at the
+    // GSS node a call of x returns to, and at those of the left-recursive
+    // alternatives of x, which can then go on after it
+    fn recover(&mut self, (l, u, i, w): Descriptor, j: usize, lookahead: Option<Lookahead>) {
+        let x = self.grammar.productions[l.production].rhs[l.dot];
+        let next = Slot {
+            production: l.production,
+            dot: l.dot + 1,
+        };
+        let y = match x {
+            Symbol::Nonterminal(y) => y,
+            _ => {
+                let error = self.forest.error(&mut *self.observer, x, i, j);
+                let z = self.get_node_p(next, w, error);
+                self.add(next, u, j, z);
+                return;
+            }
+        };
+        let v = self.create(next, u, i, w);
+        if j == i && self.analysis.nullable[y] {
+            // only the alternatives that may derive eps: the others failed
+            // the test at l already
+            self.recovering = Some(i);
+            for index in 0..self.alternatives[y].len() {
+                let l = Slot {
+                    production: self.alternatives[y][index],
+                    dot: 0,
+                };
+                if self.test(lookahead, l, i) {
+                    self.add(l, v, i, DUMMY);
                 }
-            } else {
-                // w = $
-                if let Some(t) = l.end() {
-                    // t = X
-                    let y = self.find_or_create_sppf_symbol(t, k, i);
-                    if let Some(children) = self.sppf_nodes[y].children() {
-                        if !children.iter().any(|index| match &self.sppf_nodes[*index] {
-                            SPPFNode::Packed(node_l, node_k, _) => *node_l == l && *node_k == k,
-                            _ => false,
-                        }) {
-                            let len = self.sppf_nodes.len();
-                            self.sppf_nodes[y].children_mut().unwrap().push(len);
-                            self.sppf_nodes.push(SPPFNode::Packed(l, k, vec![z]));
-                        }
-                    } else {
-                        unimplemented!()
-                    }
-                    y
-                } else {
-                    // t = l
-                    let y = self.find_or_create_sppf_intermediate(l.clone(), k, i);
-                    if let Some(children) = self.sppf_nodes[y].children() {
-                        if !children.iter().any(|index| match &self.sppf_nodes[*index] {
-                            SPPFNode::Packed(node_l, node_k, _) => *node_l == l && *node_k == k,
-                            _ => false,
-                        }) {
-                            let len = self.sppf_nodes.len();
-                            self.sppf_nodes[y].children_mut().unwrap().push(len);
-                            self.sppf_nodes.push(SPPFNode::Packed(l, k, vec![z]));
-                        }
-                    } else {
-                        unimplemented!()
-                    }
-                    y
+            }
+            return;
+        }
+        let error = self.forest.error(&mut *self.observer, x, i, j);
+        let grammar = self.grammar;
+        let recursive: Vec<Slot> = self.alternatives[y]
+            .iter()
+            .filter(|&&production| grammar.productions[production].rhs.first() == Some(&x))
+            .map(|&production| Slot { production, dot: 1 })
+            .collect();
+        let mut callers = vec![v];
+        let mut index = 0;
+        while index < callers.len() {
+            for &slot in &recursive {
+                let caller = self.create(slot, callers[index], i, DUMMY);
+                if !callers.contains(&caller) {
+                    callers.push(caller);
                 }
             }
+            index += 1;
+        }
+        for caller in callers {
+            self.pop(caller, j, error);
         }
     }
+}
 
-    fn find_or_create_sppf_symbol(&mut self, s: L::Symbol, i: usize, j: usize) -> SPPFNodeIndex {
-        for (index, node) in self.sppf_nodes.iter().enumerate() {
-            if let SPPFNode::Symbol(node_s, node_i, node_j, _) = node {
-                if *node_s == s && *node_i == i && *node_j == j {
-                    return index;
-                }
+/// Parse Γ1.
+pub fn parse<T: Token + Clone>(input: &[T]) -> Result<ParseOutcome<Sppf<T>>, ParseError> {
+    parse_with(&gamma1(), input)
+}
+
+/// Parse `input` with a runtime grammar, each terminal matching the tokens
+/// of the same kind, e.g. the bytes spelling it.
+pub fn parse_with<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+) -> Result<ParseOutcome<Sppf<T>>, ParseError> {
+    parse_observed(grammar, input, &mut ())
+}
+
+/// Like `parse_with`, also returning the GSS for inspection.
+pub fn parse_with_gss<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+) -> (Result<ParseOutcome<Sppf<T>>, ParseError>, Gss) {
+    let (result, gss, _) = parse_run(grammar, input, false);
+    (result, gss)
+}
+
+/// Like `parse_with`, also returning counters of the work done, the peak
+/// size of each structure and the time taken by each phase.
+pub fn parse_with_statistics<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+) -> (Result<ParseOutcome<Sppf<T>>, ParseError>, Statistics) {
+    parse_scheduled(grammar, input, Scheduler::default())
+}
+
+/// Like `parse_with_statistics`, taking the descriptors from R in the
+/// order of `scheduler`, so that the orders can be compared.
+pub fn parse_scheduled<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+    scheduler: Scheduler,
+) -> (Result<ParseOutcome<Sppf<T>>, ParseError>, Statistics) {
+    parse_configured(grammar, input, SppfBuilder::default(), scheduler, &mut ())
+}
+
+/// Like `parse_with`, reporting each step of the main loop to `observer`.
+pub fn parse_observed<T: Token + Clone, O: ParseObserver>(
+    grammar: &Grammar,
+    input: &[T],
+    observer: &mut O,
+) -> Result<ParseOutcome<Sppf<T>>, ParseError> {
+    let forest = SppfBuilder::default();
+    parse_configured(grammar, input, forest, Scheduler::default(), observer).0
+}
+
+/// Parse `input` building `forest`: `SppfBuilder::default()` for the SPPF,
+/// as `parse_with` does, or `Recognizer` to only tell whether it is a
+/// sentence, for a fraction of the memory and with the same error if not.
+/// The descriptors are taken from R in the order of `scheduler` and each
+/// step is reported to `observer`; the statistics of the parse come along.
+pub fn parse_configured<T: Token, O: ParseObserver, F: Forest<T>>(
+    grammar: &Grammar,
+    input: &[T],
+    forest: F,
+    scheduler: Scheduler,
+    observer: &mut O,
+) -> (Result<ParseOutcome<F::Output>, ParseError>, Statistics) {
+    let run = run(grammar, input, observer, forest, scheduler, false);
+    let (result, _, statistics) = output(grammar, input, run);
+    (result, statistics)
+}
+
+/// Like `parse_configured` with a `Recognizer`, but read the input from
+/// `reader` as it arrives, a byte being a token. Descriptors are processed
+/// position by position, reading only as far ahead as the longest
+/// production, and the sets U_j and the input are freed once their
+/// position is passed. The answer comes when the input ends, or as soon
+/// as no descriptor is left.
+///
+/// Nothing else is freed: a GSS node made at a passed position may still
+/// be popped when the nonterminal called there ends, arbitrarily later,
+/// so the GSS and the sets P grow with the input, up to a node for each
+/// return slot at each position.
+pub fn recognize_read<R: Read>(
+    grammar: &Grammar,
+    reader: R,
+) -> io::Result<Result<ParseOutcome<()>, ParseError>> {
+    stream(grammar, reader).map(|(result, _)| result)
+}
+
+// the input read so far, from position base on
+struct Window<R> {
+    reader: R,
+    base: usize,
+    bytes: VecDeque<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Window<R> {
+    // read until the window holds position j or the input ends
+    fn fill(&mut self, j: usize) -> io::Result<()> {
+        let mut chunk = [0; 8192];
+        while !self.eof && self.base + self.bytes.len() <= j {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.bytes.extend(&chunk[..n]),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
             }
         }
-        self.sppf_nodes.push(SPPFNode::Symbol(s, i, j, vec![]));
-        self.sppf_nodes.len() - 1
+        Ok(())
     }
 
-    fn find_or_create_sppf_intermediate(&mut self, l: L, i: usize, j: usize) -> SPPFNodeIndex {
-        for (index, node) in self.sppf_nodes.iter().enumerate() {
-            if let SPPFNode::Intermediate(node_l, node_i, node_j, _) = node {
-                if *node_l == l && *node_i == i && *node_j == j {
-                    return index;
-                }
-            }
+    // drop the input before position j
+    fn advance(&mut self, j: usize) {
+        let passed = (j - self.base).min(self.bytes.len());
+        self.bytes.drain(..passed);
+        self.base = j;
+    }
+
+    fn lookahead(&self, grammar: &Grammar, i: usize) -> Option<Lookahead> {
+        token_lookahead(grammar, self.bytes.get(i - self.base))
+    }
+}
+
+// recognize_read, also returning the statistics; U_j and the input are
+// freed as positions pass, but not the GSS nor P, see recognize_read
+fn stream<R: Read>(
+    grammar: &Grammar,
+    reader: R,
+) -> io::Result<(Result<ParseOutcome<()>, ParseError>, Statistics)> {
+    let started = Instant::now();
+    let mut observer = ();
+    let mut state = GSSState::new(
+        grammar,
+        &mut observer,
+        Recognizer,
+        Scheduler::ByPosition,
+        false,
+    );
+    state.statistics.time.analysis = started.elapsed();
+    let started = Instant::now();
+    // a descriptor at i reads the tokens at i up to i + reach at most
+    let reach = grammar
+        .productions
+        .iter()
+        .map(|production| production.rhs.len())
+        .max()
+        .unwrap_or(0);
+    let mut window = Window {
+        reader,
+        base: 0,
+        bytes: VecDeque::new(),
+        eof: false,
+    };
+    window.fill(reach)?;
+    state.start(window.lookahead(grammar, 0));
+    let mut position = 0;
+    while let Some(descriptor) = state.todo.pop() {
+        let (_, _, i, _) = descriptor;
+        if i > position {
+            // descriptors come by position, and none is added before i
+            state.free(i);
+            window.advance(i);
+            position = i;
         }
-        self.sppf_nodes
-            .push(SPPFNode::Intermediate(l, i, j, vec![]));
-        self.sppf_nodes.len() - 1
-    }
-}
-
-pub fn parse(input: &[u8]) {
-    use Label::*;
-    let m = input.len() - 1;
-
-    let mut graph: Graph<GSSNode<Label>, SPPFNodeIndex, Directed> = Graph::new();
-    let mut nodes = BTreeMap::new();
-    let initial_node = (L0, 0);
-    let initial_node_index = graph.add_node(initial_node);
-    nodes.insert(initial_node, initial_node_index);
-
-    let mut state = GSSState {
-        graph,
-        nodes,
-        sppf_nodes: vec![SPPFNode::Dummy],
-        initial_node_index,
-        visited: vec![BTreeSet::new(); input.len()],
-        todo: Vec::new(),
-        pop: BTreeSet::new(),
-        current_node_index: initial_node_index,
-        current_sppf_node: 0,
-        current_position: 0,
+        window.fill(i + reach)?;
+        state.process(descriptor, |j| window.lookahead(grammar, j));
+    }
+
+    // short of the end of input, m is past every position reached, so
+    // accepts only records that the end of input was expected
+    let m = window.base + window.bytes.len();
+    let end = if state.accepts(m) && window.eof {
+        Some(m)
+    } else {
+        None
     };
+    state.statistics.time.parse = started.elapsed();
+    let run = state.finish(end, vec![]);
+    let result = match run.end {
+        Some(end) => Ok(ParseOutcome { value: (), end }),
+        None => Err(run.furthest.into_error()),
+    };
+    Ok((result, run.statistics))
+}
 
-    // FIRST(S$)
-    if [b'a', b'b', b'c', b'd', b'$'].contains(&input[0]) {
-        let mut current_label = LS;
-        loop {
-            println!("{:?} {:?}", current_label, state.todo);
-            match current_label {
-                L0 => {
-                    if let Some((l, u, i, w)) = state.todo.pop() {
-                        current_label = l;
-                        state.current_node_index = u;
-                        state.current_position = i;
-                        state.current_sppf_node = w;
-                    } else {
-                        if state.sppf_nodes.iter().any(|node| {
-                            if let SPPFNode::Symbol(Symbol::NS, 0, node_m, _) = node {
-                                *node_m == m
-                            } else {
-                                false
-                            }
-                        }) {
-                            println!("Succ");
-
-                            let mut file = File::create("sppf.dot").unwrap();
-                            write!(file, "digraph {{\n").unwrap();
-                            for (i, node) in state.sppf_nodes.iter().enumerate() {
-                                let label = match node {
-                                    SPPFNode::Symbol(s, _, _, _) => format!("{:?}", s),
-                                    SPPFNode::Intermediate(_, _, _, _) => format!("I"),
-                                    SPPFNode::Packed(_, _, _) => format!("P"),
-                                    SPPFNode::Dummy => format!("D"),
-                                };
-                                write!(file, "{} [label={:?}]\n", i, label).unwrap();
-                                if let Some(children) = node.children() {
-                                    for child in children {
-                                        write!(file, "{} -> {}\n", i, child).unwrap();
-                                    }
-                                }
-                            }
-                            write!(file, "}}").unwrap();
-                            break;
-                        } else {
-                            println!("Fail");
-                            break;
-                        }
-                    }
-                }
-                LS => {
-                    if [b'a', b'c'].contains(&input[state.current_position]) {
-                        state.add(
-                            LS_0,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    if [b'a', b'b'].contains(&input[state.current_position]) {
-                        state.add(
-                            LS_1,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    if true {
-                        state.add(
-                            LS_2,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    current_label = L0;
-                }
-                LS_0 => {
-                    state.current_node_index = state.create(
-                        LS_0_1,
-                        state.current_node_index,
-                        state.current_position,
-                        state.current_sppf_node,
-                    );
-                    current_label = LA;
-                }
-                LS_0_1 => {
-                    if [b'a', b'b', b'c', b'd', b'$'].contains(&input[state.current_position]) {
-                        state.current_node_index = state.create(
-                            LS_0_2,
-                            state.current_node_index,
-                            state.current_position,
-                            state.current_sppf_node,
-                        );
-                        current_label = LS;
-                    } else {
-                        current_label = L0;
-                    }
-                }
-                LS_0_2 => {
-                    if input[state.current_position] == b'd' {
-                        let right = state.get_node_t(Symbol::TD, state.current_position);
-                        state.current_position += 1;
-                        state.current_sppf_node =
-                            state.get_node_p(L3, state.current_sppf_node, right);
-                        current_label = Ret;
-                    } else {
-                        current_label = L0;
-                    }
-                }
-                LS_1 => {
-                    state.current_node_index = state.create(
-                        LS_1_1,
-                        state.current_node_index,
-                        state.current_position,
-                        state.current_sppf_node,
-                    );
-                    current_label = LB;
-                }
-                LS_1_1 => {
-                    if [b'a', b'b', b'c', b'd', b'$'].contains(&input[state.current_position]) {
-                        state.current_node_index = state.create(
-                            LS_1_2,
-                            state.current_node_index,
-                            state.current_position,
-                            state.current_sppf_node,
-                        );
-                        current_label = LS;
-                    } else {
-                        current_label = L0;
-                    }
-                }
-                LS_1_2 => {
-                    current_label = Ret;
-                }
-                LS_2 => {
-                    let right = state.get_node_t(Symbol::Eps, state.current_position);
-                    state.current_sppf_node =
-                        state.get_node_p(Label::LS_2, state.current_sppf_node, right);
-                    current_label = Ret;
+/// A best-effort parse, see `parse_recovering`.
+#[derive(Debug, Clone)]
+pub struct Recovered<T> {
+    pub sppf: Sppf<T>,
+    /// The errors recovered from, in input order: those whose error nodes
+    /// are in `sppf`, and the input left after the start symbol.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Like `parse_with`, but recover from errors. Where the parse gets stuck
+/// expecting a symbol, the fewest tokens after which it gets further are
+/// skipped, none for a missing symbol, and an error node for the symbol
+/// spans them, but a missing nullable nonterminal derives eps; of the
+/// derivations found, those skipping the least are kept. Where the input
+/// ends too early, each symbol still missing gets an empty error node at
+/// the end.
+/// Input left after a complete start symbol is reported and left out of
+/// the forest, whose root then ends before the input does.
+pub fn parse_recovering<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+) -> Result<ParseOutcome<Recovered<T>>, ParseError> {
+    let (result, _, mut diagnostics) = parse_run(grammar, input, true);
+    result.map(|outcome| {
+        // the errors of the derivations filtered out left no error node
+        let sppf = outcome.value;
+        let errors: HashSet<(usize, usize)> = sppf
+            .nodes()
+            .filter(|&node| matches!(sppf.kind(node), NodeKind::Error(_)))
+            .map(|node| sppf.extent(node))
+            .collect();
+        let trailing = (outcome.end, input.end());
+        diagnostics.retain(|diagnostic| {
+            errors.contains(&diagnostic.skipped) || diagnostic.skipped == trailing
+        });
+        ParseOutcome {
+            value: Recovered { sppf, diagnostics },
+            end: outcome.end,
+        }
+    })
+}
+
+fn run<T: Token, O: ParseObserver, F: Nodes>(
+    grammar: &Grammar,
+    input: &[T],
+    observer: &mut O,
+    forest: F,
+    scheduler: Scheduler,
+    recover: bool,
+) -> Run<F> {
+    let m = input.end();
+    let started = Instant::now();
+    let mut state = GSSState::new(grammar, observer, forest, scheduler, recover);
+    let lookaheads: Vec<Option<Lookahead>> = (0..=m)
+        .map(|i| token_lookahead(grammar, input.at(i)))
+        .collect();
+    let lookahead = |i: usize| lookaheads[i];
+    state.statistics.time.analysis = started.elapsed();
+    let started = Instant::now();
+    state.start(lookahead(0));
+
+    let mut diagnostics = vec![];
+    // recovery in progress: the descriptors stuck at position p, the error
+    // there and the end of the tokens skipped in the last try
+    let mut recovery: Option<(Vec<Descriptor>, ParseError, usize, usize)> = None;
+    // position of the last error recovered from, m if recovery gave up
+    let mut recovered: Option<usize> = None;
+    let end = loop {
+        while let Some(descriptor) = state.todo.pop() {
+            state.process(descriptor, lookahead);
+        }
+
+        let accepted = state.accepts(m);
+        let start_end = state.ends.iter().next_back().cloned();
+        if accepted || !recover {
+            if let Some((_, error, p, q)) = recovery {
+                diagnostics.push(Diagnostic {
+                    error,
+                    skipped: (p, q),
+                });
+            }
+            break if accepted { Some(m) } else { None };
+        }
+
+        let stuck_at = state.stuck.iter().map(|&(_, _, i, _)| i).max();
+        if let Some((mut candidates, error, p, q)) = recovery.take() {
+            // some input after the skipped tokens matched
+            let progress =
+                matches!(stuck_at, Some(i) if i > q) || matches!(start_end, Some(r) if r > q);
+            if progress {
+                diagnostics.push(Diagnostic {
+                    error,
+                    skipped: (p, q),
+                });
+                recovered = Some(p);
+            } else if q < m {
+                // skip one more token
+                candidates.extend(state.stuck.drain(..).filter(|&(_, _, i, _)| i == p));
+                for &descriptor in &candidates {
+                    state.recover(descriptor, q + 1, lookahead(p));
                 }
-                LA => {
-                    if [b'a'].contains(&input[state.current_position]) {
-                        state.add(
-                            LA_3,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    if [b'c'].contains(&input[state.current_position]) {
-                        state.add(
-                            LA_4,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
+                recovery = Some((candidates, error, p, q + 1));
+                continue;
+            } else {
+                // at the end of input: go on past the error nodes, with an
+                // empty one for each symbol missing after them
+                let chained: Vec<Descriptor> = state
+                    .stuck
+                    .drain(..)
+                    .filter(|&(_, _, i, _)| i == m)
+                    .filter(|descriptor| !candidates.contains(descriptor))
+                    .collect();
+                if !chained.is_empty() {
+                    for &descriptor in &chained {
+                        state.recover(descriptor, m, lookahead(m));
                     }
-                    current_label = L0
+                    candidates.extend(chained);
+                    recovery = Some((candidates, error, p, q));
+                    continue;
                 }
-                LA_3 => {
-                    let right = state.get_node_t(Symbol::TA, state.current_position);
-                    state.current_position += 1;
-                    state.current_sppf_node = state.get_node_p(L6, state.current_sppf_node, right);
-                    current_label = Ret;
+                recovered = Some(m);
+            }
+        }
+        match stuck_at {
+            Some(p) if !matches!(recovered, Some(r) if p <= r) => {
+                // try an empty error node first, for a missing symbol
+                let candidates: Vec<Descriptor> = state
+                    .stuck
+                    .drain(..)
+                    .filter(|&(_, _, i, _)| i == p)
+                    .collect();
+                for &descriptor in &candidates {
+                    state.recover(descriptor, p, lookahead(p));
                 }
-                LA_4 => {
-                    let right = state.get_node_t(Symbol::TC, state.current_position);
-                    state.current_position += 1;
-                    state.current_sppf_node = state.get_node_p(L7, state.current_sppf_node, right);
-                    current_label = Ret;
+                recovery = Some((candidates, state.furthest.error(), p, p));
+            }
+            _ => {
+                // input left after the start symbol
+                if let Some(r) = start_end {
+                    diagnostics.push(Diagnostic {
+                        error: state.furthest.error(),
+                        skipped: (r, m),
+                    });
                 }
-                LB => {
-                    if [b'a'].contains(&input[state.current_position]) {
-                        state.add(
-                            LB_5,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    if [b'b'].contains(&input[state.current_position]) {
-                        state.add(
-                            LB_6,
-                            state.current_node_index,
-                            state.current_position,
-                            0, // dummy
-                        );
-                    }
-                    current_label = L0
+                break start_end;
+            }
+        }
+    };
+
+    state.statistics.time.parse = started.elapsed();
+    state.finish(end, diagnostics)
+}
+
+// what a run of the main loop leaves
+struct Run<F> {
+    forest: F,
+    graph: Graph<GSSNode, SPPFNodeIndex, Directed>,
+    // right extent of the derivation of the start symbol from 0 kept, if any
+    end: Option<usize>,
+    furthest: Furthest,
+    diagnostics: Vec<Diagnostic>,
+    statistics: Statistics,
+}
+
+// the result of a run, from what its forest built
+fn output<T: Token, F: Forest<T>>(
+    grammar: &Grammar,
+    input: &[T],
+    run: Run<F>,
+) -> (
+    Result<ParseOutcome<F::Output>, ParseError>,
+    Vec<Diagnostic>,
+    Statistics,
+) {
+    let Run {
+        forest,
+        end,
+        mut furthest,
+        diagnostics,
+        mut statistics,
+        ..
+    } = run;
+    let started = Instant::now();
+    let errors = !diagnostics.is_empty();
+    let output = forest.output(grammar, input, end, errors, &mut statistics);
+    statistics.time.build = started.elapsed();
+    let result = match output {
+        Some(outcome) => Ok(outcome),
+        None => {
+            if end.is_some() {
+                // every derivation was filtered out
+                let name = &grammar.nonterminals[grammar.start];
+                furthest.fail(input.end(), None::<String>, name);
+            }
+            Err(furthest.into_error())
+        }
+    };
+    (result, diagnostics, statistics)
+}
+
+type Parsed<T> = Result<ParseOutcome<Sppf<T>>, ParseError>;
+
+// parse building the SPPF, also returning the GSS and the errors recovered from
+fn parse_run<T: Token + Clone>(
+    grammar: &Grammar,
+    input: &[T],
+    recover: bool,
+) -> (Parsed<T>, Gss, Vec<Diagnostic>) {
+    let forest = SppfBuilder::default();
+    let run = run(
+        grammar,
+        input,
+        &mut (),
+        forest,
+        Scheduler::default(),
+        recover,
+    );
+    let gss = run.forest.gss(&run.graph);
+    let (result, diagnostics, _) = output(grammar, input, run);
+    (result, gss, diagnostics)
+}
+
+#[test]
+fn lookahead_tests() {
+    // every descriptor at the start of an alternative passed test(I[i], X, α)
+    struct Starts(Vec<(Slot, usize)>);
+    impl ParseObserver for Starts {
+        fn descriptor_added(&mut self, slot: Slot, _: usize, position: usize, _: Option<NodeId>) {
+            if slot.dot == 0 {
+                self.0.push((slot, position));
+            }
+        }
+    }
+    let grammar = gamma1();
+    let analysis = Analysis::new(&grammar);
+    let input = b"aabd";
+    let mut starts = Starts(vec![]);
+    parse_observed(&grammar, input, &mut starts).unwrap();
+    assert!(!starts.0.is_empty());
+    for &(slot, position) in &starts.0 {
+        let lookahead = token_lookahead(&grammar, input.at(position));
+        assert!(analysis.test(lookahead, slot));
+    }
+}
+
+#[test]
+fn recognizer() {
+    use crate::grammar::Grammar;
+    fn recognize(grammar: &Grammar, input: &[u8]) -> Result<ParseOutcome<()>, ParseError> {
+        parse_configured(grammar, input, Recognizer, Scheduler::default(), &mut ()).0
+    }
+    let grammar = gamma1();
+    for input in &[&b"aabd"[..], b"", b"abd", b"ad", b"aabdx", b"cd"] {
+        let parsed = parse_with(&grammar, input);
+        let recognized = recognize(&grammar, input);
+        assert_eq!(recognized.is_ok(), parsed.is_ok());
+        if let (Err(recognized), Err(parsed)) = (recognized, parsed) {
+            assert_eq!(recognized, parsed);
+        }
+    }
+    let grammar = Grammar::from_bnf(r#"S ::= S S | S | "a" ;"#).unwrap();
+    assert_eq!(recognize(&grammar, &[b'a'; 30][..]).unwrap().end, 30);
+}
+
+#[test]
+#[cfg(not(feature = "linear-scan"))]
+fn keyed_lookup() {
+    use crate::grammar::Grammar;
+    // every node found by key is the one a scan finds
+    let check = |grammar: &Grammar, input: &[u8], recover: bool| {
+        let run = run(
+            grammar,
+            input,
+            &mut (),
+            SppfBuilder::default(),
+            Scheduler::default(),
+            recover,
+        );
+        let forest = run.forest;
+        for (index, node) in forest.sppf_nodes.iter().enumerate() {
+            match *node {
+                SPPFNode::Symbol(s, i, j, _) => {
+                    assert_eq!(forest.symbol_nodes[&(s, i, j)], index);
+                    assert_eq!(forest.scan_symbol(s, i, j), Some(index));
                 }
-                LB_5 => {
-                    let right = state.get_node_t(Symbol::TA, state.current_position);
-                    state.current_position += 1;
-                    state.current_sppf_node =
-                        state.get_node_p(LB_5_1, state.current_sppf_node, right);
-                    current_label = Ret;
+                SPPFNode::Intermediate(l, i, j, _) => {
+                    assert_eq!(forest.intermediate_nodes[&(l, i, j)], index);
+                    assert_eq!(forest.scan_intermediate(l, i, j), Some(index));
                 }
-                LB_6 => {
-                    let right = state.get_node_t(Symbol::TB, state.current_position);
-                    state.current_position += 1;
-                    state.current_sppf_node = state.get_node_p(L9, state.current_sppf_node, right);
-                    current_label = Ret;
+                SPPFNode::Error(x, i, j) => {
+                    assert_eq!(forest.error_nodes[&(x, i, j)], index);
+                    assert_eq!(forest.scan_error(x, i, j), Some(index));
                 }
-                Ret => {
-                    state.pop(
-                        state.current_node_index,
-                        state.current_position,
-                        state.current_sppf_node,
-                    );
-                    current_label = L0;
+                SPPFNode::Packed(_, _, _) | SPPFNode::Dummy => {}
+            }
+            for &packed in node.children().into_iter().flatten() {
+                if let SPPFNode::Packed(l, k, _) = forest.sppf_nodes[packed] {
+                    assert!(forest.packed_nodes.contains(&(index, l, k)));
+                    assert!(forest.scan_packed(index, l, k));
                 }
-                _ => {
-                    panic!("Unreachable {:?}", current_label);
+            }
+        }
+        let packed = forest
+            .sppf_nodes
+            .iter()
+            .filter(|node| matches!(node, SPPFNode::Packed(_, _, _)));
+        assert_eq!(packed.count(), forest.packed_nodes.len());
+    };
+    let grammar = Grammar::from_bnf(r#"S ::= S S | "a" ;"#).unwrap();
+    check(&grammar, &[b'a'; 8], false);
+    check(&gamma1(), b"aabd", false);
+    check(&gamma1(), b"axxd", true);
+}
+
+#[test]
+fn late_edges() {
+    use crate::grammar::Grammar;
+    // the GSS nodes popped, and the edges then made from them
+    #[derive(Default)]
+    struct Late {
+        popped: HashSet<usize>,
+        edges: usize,
+    }
+    impl ParseObserver for Late {
+        fn gss_edge_created(&mut self, from: usize, _: usize, _: Option<NodeId>) {
+            if self.popped.contains(&from) {
+                self.edges += 1;
+            }
+        }
+        fn pop(&mut self, gss: usize, _: usize, _: NodeId) {
+            self.popped.insert(gss);
+        }
+    }
+    // X is called at 1 from S ::= "a" X and, once Y is done, from
+    // S ::= Y X; the second call finds X ::= A . "b" popped, and P
+    // replays the ε of A on the new edge
+    let grammar =
+        Grammar::from_bnf(r#"S ::= "a" X | Y X ; X ::= A "b" ; A ::= ; Y ::= "a" ;"#).unwrap();
+    let mut late = Late::default();
+    let sppf = parse_observed(&grammar, &b"ab"[..], &mut late)
+        .unwrap()
+        .value;
+    assert!(late.edges > 0);
+    let mut trees: Vec<String> = sppf
+        .trees()
+        .map(|tree| tree.display(&grammar).to_string())
+        .collect();
+    trees.sort();
+    assert_eq!(trees, ["(S (Y a) (X (A) b))", "(S a (X (A) b))"]);
+}
+
+#[test]
+fn recognizer_observed() {
+    use crate::grammar::Grammar;
+    // the descriptors added, with the right extents of their SPPF nodes
+    #[derive(Default)]
+    struct Extents {
+        // right extent of each SPPF node, if built
+        nodes: HashMap<NodeId, usize>,
+        added: Vec<(Slot, usize, usize, Option<usize>)>,
+    }
+    impl ParseObserver for Extents {
+        fn descriptor_added(&mut self, slot: Slot, gss: usize, i: usize, sppf: Option<NodeId>) {
+            let extent = sppf.map(|node| self.nodes.get(&node).map_or(node.0, |&j| j));
+            self.added.push((slot, gss, i, extent));
+        }
+        fn sppf_node_created(&mut self, sppf: NodeId, _: NodeKind, (_, j): (usize, usize)) {
+            self.nodes.insert(sppf, j);
+        }
+    }
+    // A derives eps at 0, which a recognizer passes on as position 0
+    let grammar = Grammar::from_bnf(r#"S ::= A "a" | "a" A ; A ::= ;"#).unwrap();
+    let mut parsed = Extents::default();
+    parse_observed(&grammar, &b"a"[..], &mut parsed).unwrap();
+    let mut recognized = Extents::default();
+    let scheduler = Scheduler::default();
+    let (result, _) = parse_configured(&grammar, &b"a"[..], Recognizer, scheduler, &mut recognized);
+    result.unwrap();
+    assert!(recognized.nodes.is_empty());
+    assert!(recognized
+        .added
+        .iter()
+        .any(|&(_, _, _, extent)| extent == Some(0)));
+    assert_eq!(recognized.added, parsed.added);
+}
+
+#[test]
+fn streaming() {
+    use crate::grammar::Grammar;
+    // one byte per read
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&byte, rest)), Some(first)) => {
+                    *first = byte;
+                    self.0 = rest;
+                    Ok(1)
                 }
+                _ => Ok(0),
             }
         }
-        let mut f = File::create("gss.dot").unwrap();
-        write!(f, "{:?}", Dot::with_config(&state.graph, &[])).unwrap();
-    } else {
-        println!("Fail");
-        return;
     }
+    let grammar = gamma1();
+    for input in &[&b"aabd"[..], b"", b"abd", b"ad", b"aabdx", b"cd"] {
+        let streamed = recognize_read(&grammar, Trickle(input)).unwrap();
+        let (recognized, _) =
+            parse_configured(&grammar, input, Recognizer, Scheduler::default(), &mut ());
+        assert_eq!(streamed, recognized);
+    }
+
+    // left recursion needs the same U_j at each position, so with the
+    // passed ones freed their peak does not grow with the input
+    let grammar = Grammar::from_bnf(r#"S ::= S "a" | "a" ;"#).unwrap();
+    let visited = |n: usize| {
+        let (result, statistics) = stream(&grammar, io::repeat(b'a').take(n as u64)).unwrap();
+        assert_eq!(result.unwrap().end, n);
+        statistics.memory.visited
+    };
+    assert_eq!(visited(10_000), visited(100));
+}
+
+#[test]
+fn recovery() {
+    use crate::grammar::Grammar;
+    fn recover(grammar: &Grammar, input: &[u8]) -> (Vec<String>, Vec<(usize, usize)>) {
+        let recovered = parse_recovering(grammar, input).unwrap().value;
+        let trees: Vec<String> = recovered
+            .sppf
+            .trees()
+            .map(|tree| tree.display(grammar).to_string())
+            .collect();
+        let skipped: Vec<(usize, usize)> = recovered
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.skipped)
+            .collect();
+        (trees, skipped)
+    }
+    let grammar = gamma1();
+    assert_eq!(recover(&grammar, b"aabd").1, []);
+    assert_eq!(
+        recover(&grammar, b"axxxxd"),
+        (vec!["(S (A a) (error S) d)".to_owned()], vec![(1, 5)])
+    );
+    // a missing terminal
+    assert_eq!(
+        recover(&grammar, b"d"),
+        (vec!["(S (error A) (S) d)".to_owned()], vec![(0, 0)])
+    );
+
+    // input cut short: every missing symbol gets an empty error node at the
+    // end, but a nullable one derives eps
+    assert_eq!(
+        recover(&grammar, b"cc"),
+        (
+            vec!["(S (A c) (S (A c) (S) (error d)) (error d))".to_owned()],
+            vec![(2, 2)]
+        )
+    );
+    let grammar = Grammar::from_bnf(r#"E ::= E "+" E | "(" E ")" | "n" ;"#).unwrap();
+    assert_eq!(
+        recover(&grammar, b"(n+"),
+        (
+            vec!["(E ( (E (E n) + (error E)) (error )))".to_owned()],
+            vec![(3, 3)]
+        )
+    );
+    let grammar = Grammar::from_bnf(r#"S ::= "a" S "b" | ;"#).unwrap();
+    assert_eq!(
+        recover(&grammar, b"a"),
+        (vec!["(S a (S) (error b))".to_owned()], vec![(1, 1)])
+    );
+
+    assert_eq!(
+        recover(&grammar, b"aa"),
+        (
+            vec!["(S a (S a (S) (error b)) (error b))".to_owned()],
+            vec![(2, 2)]
+        )
+    );
+
+    // left recursion goes on after an error node for the recursive nonterminal
+    let grammar = Grammar::from_bnf(r#"E ::= E "+" "n" | "n" ;"#).unwrap();
+    assert_eq!(
+        recover(&grammar, b"+n+n+n"),
+        (
+            vec!["(E (E (E (error E) + n) + n) + n)".to_owned()],
+            vec![(0, 0)]
+        )
+    );
+    let grammar = Grammar::from_bnf(r#"S ::= S "a" | ;"#).unwrap();
+    assert_eq!(
+        recover(&grammar, b"baa"),
+        (vec!["(S (S (error S) a) a)".to_owned()], vec![(0, 1)])
+    );
+    assert_eq!(
+        recover(&grammar, b"baaaa"),
+        (
+            vec!["(S (S (S (S (error S) a) a) a) a)".to_owned()],
+            vec![(0, 1)]
+        )
+    );
+
+    // the recovery at 2 only led to derivations filtered out
+    let grammar = Grammar::from_bnf(r#"E ::= E "+" E | "(" E ")" | "n" ;"#).unwrap();
+    assert_eq!(
+        recover(&grammar, b")n))"),
+        (
+            vec!["(E (error () (E n) ))".to_owned()],
+            vec![(0, 1), (3, 4)]
+        )
+    );
+
+    let grammar = gamma1();
+    let recovered = parse_recovering(&grammar, b"aabdx").unwrap();
+    assert_eq!(recovered.end, 5);
+    let diagnostic = &recovered.value.diagnostics[0];
+    assert_eq!(diagnostic.error.position, 4);
+    assert!(diagnostic.error.expected.contains("d"));
+    assert!(parse_with(&grammar, b"aabdx").is_err());
 }
@@ -0,0 +1,116 @@
+use std::ops::Range;
+use std::str;
+
+/// A token, matched against the terminal with the same name as its kind.
+pub trait Token {
+    fn kind(&self) -> &str;
+
+    /// Byte range of the token in the source text, if known.
+    fn span(&self) -> Option<Range<usize>> {
+        None
+    }
+}
+
+/// A byte is a token of the single-character terminal it spells; bytes
+/// that are not ASCII match no terminal.
+impl Token for u8 {
+    fn kind(&self) -> &str {
+        str::from_utf8(std::slice::from_ref(self)).unwrap_or("")
+    }
+}
+
+/// A token with the byte range of its source text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Spanned<T> {
+    pub token: T,
+    pub span: Range<usize>,
+}
+
+impl<T: Token> Token for Spanned<T> {
+    fn kind(&self) -> &str {
+        self.token.kind()
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        Some(self.span.clone())
+    }
+}
+
+/// Parser input: tokens by position, followed by an end-of-input symbol.
+///
+/// Reading at or past the end yields `None` instead of panicking, so the
+/// engines detect the end of input themselves and callers no longer
+/// terminate their input with `$`.
+pub trait Input {
+    type Token: Token;
+
+    /// Position of the end of input, i.e. the number of tokens.
+    fn end(&self) -> usize;
+
+    /// Token at `pos`, `None` being the end of input.
+    fn at(&self, pos: usize) -> Option<&Self::Token>;
+
+    /// Byte offset in the source text of position `pos`: where its token
+    /// starts, or where the last token ends for the end of input. Tokens
+    /// without a span are taken to be one byte each.
+    fn offset(&self, pos: usize) -> usize {
+        match self.at(pos) {
+            Some(token) => token.span().map_or(pos, |span| span.start),
+            None if pos > 0 => self.source_span((pos - 1, pos)).end,
+            None => 0,
+        }
+    }
+
+    /// Byte range in the source text of the tokens in `extent`.
+    fn source_span(&self, extent: (usize, usize)) -> Range<usize> {
+        let (i, j) = extent;
+        if i == j {
+            let offset = self.offset(i);
+            return offset..offset;
+        }
+        let end = self
+            .at(j - 1)
+            .and_then(|token| token.span())
+            .map_or(j, |span| span.end);
+        self.offset(i)..end
+    }
+}
+
+impl<T: Token> Input for [T] {
+    type Token = T;
+
+    fn end(&self) -> usize {
+        self.len()
+    }
+
+    fn at(&self, pos: usize) -> Option<&T> {
+        self.get(pos)
+    }
+}
+
+#[test]
+fn end_of_input() {
+    use crate::{future, graph, manual, pg};
+    // S is nullable, so the empty input is a sentence
+    assert_eq!(graph::parse(b"").unwrap().end, 0);
+    assert_eq!(manual::parse(b"").unwrap().end, 0);
+    assert_eq!(future::parse(b"").unwrap().end, 0);
+    assert_eq!(pg::parse(b"").unwrap().end, 0);
+    // a trailing $ is no longer needed, nor accepted
+    assert_eq!(graph::parse(b"aabd").unwrap().end, 4);
+    assert_eq!(manual::parse(b"aabd").unwrap().value, 2);
+    // every engine takes both alternatives of A ::= a | c
+    for input in [&b"aabd"[..], b"cd", b"acbdd"].iter() {
+        assert_eq!(graph::parse(input).unwrap().end, input.len());
+        assert_eq!(manual::parse(input).unwrap().end, input.len());
+        assert_eq!(future::parse(input).unwrap().end, input.len());
+        assert_eq!(pg::parse(input).unwrap().end, input.len());
+    }
+    // each error is found at the offending byte
+    for &(input, position) in [(&b"aabd$"[..], 4), (b"add", 2), (b"\xff", 0)].iter() {
+        assert_eq!(graph::parse(input).unwrap_err().position, position);
+        assert_eq!(manual::parse(input).unwrap_err().position, position);
+        assert_eq!(future::parse(input).unwrap_err().position, position);
+        assert_eq!(pg::parse(input).unwrap_err().position, position);
+    }
+}
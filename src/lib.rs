@@ -1,5 +1,21 @@
+pub mod analysis;
+pub mod bnf;
 mod common;
+pub mod correct;
+pub mod count;
+pub mod dot;
+pub mod filter;
 pub mod future;
+pub mod grammar;
 pub mod graph;
+pub mod input;
 pub mod manual;
+pub mod observer;
+pub mod outcome;
 pub mod pg;
+pub mod scheduler;
+pub mod semiring;
+pub mod span;
+pub mod sppf;
+pub mod statistics;
+pub mod tree;
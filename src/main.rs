@@ -1,8 +1,111 @@
-use gll_test::pg::parse;
+use gll_test::grammar::{gamma1, Grammar};
+use gll_test::scheduler::Scheduler;
+use gll_test::{graph, pg};
 use std::io::{self, BufRead};
+use std::{env, fs, process};
 
+// Parse each line of stdin with Γ1, or with the grammar in the file given
+// as argument. With --count, print the number of derivations instead of
+// the trees; with --recover, print the errors recovered from and the
+// trees of broken lines too; with --recognize, only print whether the line
+// is a sentence; with --stats, print what the parse took. With --stream,
+// recognize the whole of stdin as it is read instead.
 fn main() {
+    let count = env::args().skip(1).any(|arg| arg == "--count");
+    let recover = env::args().skip(1).any(|arg| arg == "--recover");
+    let recognize = env::args().skip(1).any(|arg| arg == "--recognize");
+    let stats = env::args().skip(1).any(|arg| arg == "--stats");
+    let stream = env::args().skip(1).any(|arg| arg == "--stream");
+    let grammar = env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .map(|path| {
+            let text = fs::read_to_string(&path).unwrap_or_else(|err| {
+                eprintln!("{}: {}", path, err);
+                process::exit(1);
+            });
+            Grammar::from_bnf(&text).unwrap_or_else(|err| {
+                eprintln!("{}:{}", path, err);
+                process::exit(1);
+            })
+        });
+    let gamma1 = gamma1();
+    if stream {
+        let grammar = grammar.as_ref().unwrap_or(&gamma1);
+        match graph::recognize_read(grammar, io::stdin().lock()) {
+            Ok(Ok(_)) => println!("ok"),
+            Ok(Err(err)) => println!("{}", err),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
     for line in io::stdin().lock().lines() {
-        parse(line.unwrap().as_bytes());
+        let line = line.unwrap();
+        match &grammar {
+            _ if recover => {
+                let grammar = grammar.as_ref().unwrap_or(&gamma1);
+                match graph::parse_recovering(grammar, line.as_bytes()) {
+                    Ok(outcome) => {
+                        for diagnostic in &outcome.value.diagnostics {
+                            println!("{}", diagnostic);
+                        }
+                        for tree in outcome.value.sppf.trees() {
+                            println!("{}", tree.resugar(grammar).display(grammar));
+                        }
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+            _ if recognize => {
+                let grammar = grammar.as_ref().unwrap_or(&gamma1);
+                let input = line.as_bytes();
+                let recognizer = graph::Recognizer;
+                match graph::parse_configured(
+                    grammar,
+                    input,
+                    recognizer,
+                    Scheduler::default(),
+                    &mut (),
+                )
+                .0
+                {
+                    Ok(_) => println!("ok"),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            _ if stats => {
+                let grammar = grammar.as_ref().unwrap_or(&gamma1);
+                let (parsed, statistics) = graph::parse_with_statistics(grammar, line.as_bytes());
+                if let Err(err) = parsed {
+                    println!("{}", err);
+                }
+                println!("{}", statistics);
+            }
+            _ if count => {
+                let parsed = match &grammar {
+                    Some(grammar) => graph::parse_with(grammar, line.as_bytes()),
+                    None => graph::parse(line.as_bytes()),
+                };
+                match parsed {
+                    Ok(outcome) => println!("{}", outcome.value.count()),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            Some(grammar) => match graph::parse_with(grammar, line.as_bytes()) {
+                Ok(outcome) => {
+                    for tree in outcome.value.trees() {
+                        println!("{}", tree.resugar(grammar).display(grammar));
+                    }
+                }
+                Err(err) => println!("{}", err),
+            },
+            None => match pg::parse(line.as_bytes()) {
+                Ok(outcome) => println!("{:?}", outcome.value),
+                Err(err) => println!("{}", err),
+            },
+        }
     }
 }
@@ -1,15 +1,12 @@
+use crate::common::{Gamma1, S_ASD, S_BS};
+use crate::outcome::{ParseError, ParseOutcome};
 use std::collections::LinkedList;
-use std::str;
 
 // SS -> S $
 // S -> A S d | B S | eps
 // A -> a | c
 // B -> a | b
-// First(SS) = {a, b, c, eps}
-// First(S) = {a, b, c, eps}
-// Follow(S) = {d, $}
-// First(A) = {a, c}
-// First(B) = {a, b}
+// lookahead sets are computed by analysis::Analysis
 
 #[derive(Clone, Debug)]
 #[allow(non_camel_case_types)]
@@ -36,8 +33,12 @@ struct Cont {
     stack: Vec<Label>,
 }
 
-pub fn parse(input: &[u8]) {
+/// Parse Γ1, counting the derivations of `input`.
+pub fn parse(input: &[u8]) -> Result<ParseOutcome<usize>, ParseError> {
     use Label::*;
+    let g = Gamma1::new(input.len());
+    let mut matches = 0;
+    let mut end = 0;
     let mut todo: LinkedList<Cont> = LinkedList::new();
     let mut stack: Vec<Label> = Vec::new();
     stack.push(Succ);
@@ -48,13 +49,10 @@ pub fn parse(input: &[u8]) {
     });
     loop {
         if let Some(cont) = todo.pop_back() {
-            println!("at {} CONT: {:?} STACK: {:?}", cont.pos, cont, cont.stack);
             match cont.label {
                 Succ => {
-                    println!(
-                        "Found match! {}",
-                        str::from_utf8(&input[..cont.pos]).unwrap()
-                    );
+                    matches += 1;
+                    end = cont.pos;
                 }
                 SS => {
                     // SS -> .S $
@@ -68,16 +66,15 @@ pub fn parse(input: &[u8]) {
                     })
                 }
                 SS_S => {
-                    if input[cont.pos] == b'$' {
-                        let mut pos = cont.pos;
+                    if g.expect_end(&input[cont.pos..], "SS") {
+                        let pos = cont.pos;
                         let mut stack = cont.stack.clone();
-                        pos += 1;
                         let label = stack.pop().unwrap();
                         todo.push_back(Cont { label, pos, stack });
                     }
                 }
                 S => {
-                    if input[cont.pos] == b'a' || input[cont.pos] == b'c' {
+                    if g.test(&input[cont.pos..], S_ASD) {
                         // S -> A S d
                         let pos = cont.pos;
                         let stack = cont.stack.clone();
@@ -87,7 +84,7 @@ pub fn parse(input: &[u8]) {
                             stack,
                         });
                     }
-                    if input[cont.pos] == b'a' || input[cont.pos] == b'b' {
+                    if g.test(&input[cont.pos..], S_BS) {
                         // S -> B S
                         let pos = cont.pos;
                         let stack = cont.stack.clone();
@@ -132,7 +129,7 @@ pub fn parse(input: &[u8]) {
                 }
                 S1_AS => {
                     // S -> A S .d
-                    if input[cont.pos] == b'd' {
+                    if g.expect(&input[cont.pos..], b'd', "S") {
                         let mut pos = cont.pos;
                         let mut stack = cont.stack.clone();
                         pos += 1;
@@ -177,7 +174,14 @@ pub fn parse(input: &[u8]) {
                     todo.push_back(Cont { label, pos, stack });
                 }
                 A => {
-                    if input[cont.pos] == b'a' {
+                    if g.expect(&input[cont.pos..], b'a', "A") {
+                        let mut pos = cont.pos;
+                        let mut stack = cont.stack.clone();
+                        pos += 1;
+                        let label = stack.pop().unwrap();
+                        todo.push_back(Cont { label, pos, stack });
+                    }
+                    if g.expect(&input[cont.pos..], b'c', "A") {
                         let mut pos = cont.pos;
                         let mut stack = cont.stack.clone();
                         pos += 1;
@@ -186,14 +190,14 @@ pub fn parse(input: &[u8]) {
                     }
                 }
                 B => {
-                    if input[cont.pos] == b'a' {
+                    if g.expect(&input[cont.pos..], b'a', "B") {
                         let mut pos = cont.pos;
                         let mut stack = cont.stack.clone();
                         let label = stack.pop().unwrap();
                         pos += 1;
                         todo.push_back(Cont { label, pos, stack });
                     }
-                    if input[cont.pos] == b'b' {
+                    if g.expect(&input[cont.pos..], b'b', "B") {
                         let mut pos = cont.pos;
                         let mut stack = cont.stack.clone();
                         let label = stack.pop().unwrap();
@@ -206,4 +210,12 @@ pub fn parse(input: &[u8]) {
             break;
         }
     }
+    if matches > 0 {
+        Ok(ParseOutcome {
+            value: matches,
+            end,
+        })
+    } else {
+        Err(g.error())
+    }
 }
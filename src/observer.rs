@@ -0,0 +1,220 @@
+use crate::grammar::{Grammar, Slot};
+use crate::sppf::{NodeId, NodeKind};
+use std::io::{self, Write};
+
+/// Receives the events of the `graph` main loop as they happen.
+///
+/// GSS nodes are numbered in creation order, the initial node being 0;
+/// SPPF nodes are numbered in creation order too, `None` standing for the
+/// dummy node. These are the `NodeId`s of the resulting `Sppf` only if it
+/// was not filtered: with priorities, marks or recovered errors, the
+/// filtered forest is built anew and its nodes numbered afresh. With a
+/// `Recognizer`, which builds no SPPF, the `NodeId` of a node is its right
+/// extent instead. Every method defaults to doing nothing.
+pub trait ParseObserver {
+    /// Descriptor (L, u, i, w) added to R.
+    fn descriptor_added(
+        &mut self,
+        _slot: Slot,
+        _gss: usize,
+        _position: usize,
+        _sppf: Option<NodeId>,
+    ) {
+    }
+
+    /// Descriptor (L, u, i, w) taken from R.
+    fn descriptor_dequeued(
+        &mut self,
+        _slot: Slot,
+        _gss: usize,
+        _position: usize,
+        _sppf: Option<NodeId>,
+    ) {
+    }
+
+    /// GSS node created for a return slot, `None` for the initial node.
+    fn gss_node_created(&mut self, _gss: usize, _slot: Option<Slot>, _position: usize) {}
+
+    /// GSS edge created from `from` to `to`.
+    fn gss_edge_created(&mut self, _from: usize, _to: usize, _sppf: Option<NodeId>) {}
+
+    /// GSS node popped at `position` with the SPPF node of the finished nonterminal.
+    fn pop(&mut self, _gss: usize, _position: usize, _sppf: NodeId) {}
+
+    /// SPPF node created.
+    fn sppf_node_created(&mut self, _sppf: NodeId, _kind: NodeKind, _extent: (usize, usize)) {}
+}
+
+impl ParseObserver for () {}
+
+/// Writes each event as a line of JSON, e.g.
+/// `{"event":"add","slot":"S ::= A . S d","gss":0,"position":1,"sppf":3}`.
+///
+/// The first write error stops the trace and is returned by `finish`.
+pub struct JsonTrace<'g, W: Write> {
+    grammar: &'g Grammar,
+    writer: W,
+    error: Option<io::Error>,
+}
+
+// JSON string literal
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// JSON number or null
+fn id(sppf: Option<NodeId>) -> String {
+    match sppf {
+        Some(node) => node.0.to_string(),
+        None => "null".to_owned(),
+    }
+}
+
+impl<'g, W: Write> JsonTrace<'g, W> {
+    pub fn new(grammar: &'g Grammar, writer: W) -> JsonTrace<'g, W> {
+        JsonTrace {
+            grammar,
+            writer,
+            error: None,
+        }
+    }
+
+    /// The writer, or the first error met while writing to it.
+    pub fn finish(self) -> io::Result<W> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.writer),
+        }
+    }
+
+    fn event(&mut self, event: &str, fields: &[(&str, String)]) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut line = format!("{{\"event\":{}", quote(event));
+        for (name, value) in fields {
+            line.push_str(&format!(",{}:{}", quote(name), value));
+        }
+        line.push('}');
+        if let Err(error) = writeln!(self.writer, "{}", line) {
+            self.error = Some(error);
+        }
+    }
+
+    fn slot(&self, slot: Slot) -> String {
+        quote(&self.grammar.display_slot(slot).to_string())
+    }
+
+    fn descriptor(
+        &mut self,
+        event: &str,
+        slot: Slot,
+        gss: usize,
+        position: usize,
+        sppf: Option<NodeId>,
+    ) {
+        let fields = [
+            ("slot", self.slot(slot)),
+            ("gss", gss.to_string()),
+            ("position", position.to_string()),
+            ("sppf", id(sppf)),
+        ];
+        self.event(event, &fields);
+    }
+}
+
+impl<'g, W: Write> ParseObserver for JsonTrace<'g, W> {
+    fn descriptor_added(&mut self, slot: Slot, gss: usize, position: usize, sppf: Option<NodeId>) {
+        self.descriptor("add", slot, gss, position, sppf);
+    }
+
+    fn descriptor_dequeued(
+        &mut self,
+        slot: Slot,
+        gss: usize,
+        position: usize,
+        sppf: Option<NodeId>,
+    ) {
+        self.descriptor("dequeue", slot, gss, position, sppf);
+    }
+
+    fn gss_node_created(&mut self, gss: usize, slot: Option<Slot>, position: usize) {
+        let slot = match slot {
+            Some(slot) => self.slot(slot),
+            None => "null".to_owned(),
+        };
+        let fields = [
+            ("gss", gss.to_string()),
+            ("slot", slot),
+            ("position", position.to_string()),
+        ];
+        self.event("gss_node", &fields);
+    }
+
+    fn gss_edge_created(&mut self, from: usize, to: usize, sppf: Option<NodeId>) {
+        let fields = [
+            ("from", from.to_string()),
+            ("to", to.to_string()),
+            ("sppf", id(sppf)),
+        ];
+        self.event("gss_edge", &fields);
+    }
+
+    fn pop(&mut self, gss: usize, position: usize, sppf: NodeId) {
+        let fields = [
+            ("gss", gss.to_string()),
+            ("position", position.to_string()),
+            ("sppf", id(Some(sppf))),
+        ];
+        self.event("pop", &fields);
+    }
+
+    fn sppf_node_created(&mut self, sppf: NodeId, kind: NodeKind, extent: (usize, usize)) {
+        let (kind, label) = match kind {
+            NodeKind::Symbol(s) => ("symbol", quote(self.grammar.symbol_name(s))),
+            NodeKind::Intermediate(l) => ("intermediate", self.slot(l)),
+            NodeKind::Packed(l, _) => ("packed", self.slot(l)),
+            NodeKind::Error(s) => ("error", quote(self.grammar.symbol_name(s))),
+        };
+        let fields = [
+            ("sppf", id(Some(sppf))),
+            ("kind", quote(kind)),
+            ("label", label),
+            ("left", extent.0.to_string()),
+            ("right", extent.1.to_string()),
+        ];
+        self.event("sppf_node", &fields);
+    }
+}
+
+#[test]
+fn json_trace() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let mut trace = JsonTrace::new(&grammar, vec![]);
+    crate::graph::parse_observed(&grammar, b"ad", &mut trace).unwrap();
+    let out = String::from_utf8(trace.finish().unwrap()).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"{"event":"gss_node","gss":0,"slot":null,"position":0}"#
+    );
+    assert!(lines
+        .contains(&r#"{"event":"add","slot":"S ::= . A S d","gss":0,"position":0,"sppf":null}"#));
+    assert!(lines.contains(
+        &r#"{"event":"sppf_node","sppf":1,"kind":"symbol","label":"a","left":0,"right":1}"#
+    ));
+    assert!(lines
+        .iter()
+        .all(|line| line.starts_with("{\"event\":") && line.ends_with('}')));
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+/// Result of a successful parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOutcome<T> {
+    pub value: T,
+    /// Input position where the parse ended.
+    pub end: usize,
+}
+
+/// Why a parse failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Furthest input position the parser reached, a token index;
+    /// `Input::offset` turns it into a byte offset in the source.
+    pub position: usize,
+    /// Terminals that would have been accepted at `position`, `$` standing
+    /// for the end of input.
+    pub expected: BTreeSet<String>,
+    /// Nonterminals whose productions were being tried at `position`.
+    pub context: Vec<String>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "parse error at {}", self.position)?;
+        if !self.expected.is_empty() {
+            let expected: Vec<&str> = self.expected.iter().map(String::as_str).collect();
+            write!(f, ": expected one of {}", expected.join(", "))?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " while parsing {}", self.context.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+/// An error that `graph::parse_recovering` got past by matching an
+/// expected symbol with an error node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    /// Extent of the tokens skipped in place of the expected symbol, empty
+    /// if it was missing; for trailing input after the start symbol, that
+    /// input.
+    pub skipped: (usize, usize),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (i, j) = self.skipped;
+        write!(f, "{}, skipped {}..{}", self.error, i, j)
+    }
+}
+
+/// The furthest failure seen so far, turned into a `ParseError` when the
+/// whole parse fails.
+#[derive(Debug, Default)]
+pub(crate) struct Furthest {
+    position: usize,
+    expected: BTreeSet<String>,
+    context: Vec<String>,
+}
+
+impl Furthest {
+    pub(crate) fn fail<I>(&mut self, position: usize, expected: I, nonterminal: &str)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        if position > self.position {
+            *self = Furthest {
+                position,
+                ..Furthest::default()
+            };
+        }
+        if position == self.position {
+            self.expected
+                .extend(expected.into_iter().map(|name| name.into()));
+            if !self.context.iter().any(|name| name == nonterminal) {
+                self.context.push(nonterminal.to_owned());
+            }
+        }
+    }
+
+    pub(crate) fn error(&self) -> ParseError {
+        ParseError {
+            position: self.position,
+            expected: self.expected.clone(),
+            context: self.context.clone(),
+        }
+    }
+
+    pub(crate) fn into_error(self) -> ParseError {
+        ParseError {
+            position: self.position,
+            expected: self.expected,
+            context: self.context,
+        }
+    }
+}
+
+#[test]
+fn parse_errors() {
+    use crate::{future, graph, manual, pg};
+    let error = |position: usize, expected: &[&str], context: &[&str]| ParseError {
+        position,
+        expected: expected.iter().map(|&name| name.to_owned()).collect(),
+        context: context.iter().map(|&name| name.to_owned()).collect(),
+    };
+    // after c, S ::= A . S d needs an S or the d
+    let c = error(1, &["a", "b", "c", "d"], &["S"]);
+    assert_eq!(graph::parse(b"c").unwrap_err(), c);
+    assert_eq!(manual::parse(b"c").unwrap_err(), c);
+    assert_eq!(future::parse(b"c").unwrap_err(), c);
+    assert_eq!(pg::parse(b"c").unwrap_err(), c);
+    assert_eq!(
+        c.to_string(),
+        "parse error at 1: expected one of a, b, c, d while parsing S"
+    );
+    // a second d closes nothing: only the end of input fits, which the
+    // hand-written engines check in SS ::= S $
+    let add = error(2, &["$"], &["S"]);
+    assert_eq!(graph::parse(b"add").unwrap_err(), add);
+    assert_eq!(pg::parse(b"add").unwrap_err(), add);
+    let add = error(2, &["$"], &["SS"]);
+    assert_eq!(manual::parse(b"add").unwrap_err(), add);
+    assert_eq!(future::parse(b"add").unwrap_err(), add);
+}
@@ -1,3 +1,5 @@
+use crate::input::{Spanned, Token};
+use crate::outcome::{ParseError, ParseOutcome};
 use gll_pg_core::LogosToken;
 use gll_pg_macros::gll;
 use logos::Logos;
@@ -21,6 +23,35 @@ pub enum PaperToken {
     Td,
 }
 
+/// Tokens match the terminals of `grammar::gamma1`.
+impl Token for PaperToken {
+    fn kind(&self) -> &str {
+        match self {
+            PaperToken::Ta => "a",
+            PaperToken::Tb => "b",
+            PaperToken::Tc => "c",
+            PaperToken::Td => "d",
+            _ => "",
+        }
+    }
+}
+
+/// Lex `source` for `graph::parse`, skipping spaces.
+pub fn lex(source: &str) -> Vec<Spanned<PaperToken>> {
+    let mut lexer = PaperToken::lexer(source);
+    let mut tokens = vec![];
+    while lexer.token != PaperToken::End {
+        if lexer.token != PaperToken::_Eps {
+            tokens.push(Spanned {
+                token: lexer.token.clone(),
+                span: lexer.range(),
+            });
+        }
+        lexer.advance();
+    }
+    tokens
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum A {
     A,
@@ -70,10 +101,23 @@ impl Parser {
     }
 }
 
-pub fn parse(input: &[u8]) {
-    let mut lexer = PaperToken::lexer(str::from_utf8(input).unwrap());
-    let res = Parser::parse(&mut lexer);
-    println!("{:?}", res);
+/// Parse Γ1, returning every derivation of `input`.
+pub fn parse(input: &[u8]) -> Result<ParseOutcome<Vec<S>>, ParseError> {
+    let res = match str::from_utf8(input) {
+        Ok(input) => Parser::parse(&mut PaperToken::lexer(input)),
+        Err(_) => vec![],
+    };
+    if res.is_empty() {
+        // the generated parser reports nothing on failure, so rerun the
+        // input through graph::parse to locate the error
+        crate::graph::parse(input)?;
+        // both implement Γ1, so this is a bug in one of them
+        unreachable!("graph::parse accepted input rejected by pg::parse");
+    }
+    Ok(ParseOutcome {
+        value: res,
+        end: input.len(),
+    })
 }
 
 #[test]
@@ -95,3 +139,57 @@ fn gll() {
         ]
     );
 }
+
+#[test]
+fn graph_tokens() {
+    use crate::grammar::Symbol;
+    let tokens = lex("a a b d");
+    let sppf = crate::graph::parse(&tokens).unwrap().value;
+    assert_eq!(sppf.trees().count(), 2);
+    let leaves: Vec<&Spanned<PaperToken>> = sppf
+        .nodes()
+        .filter(|&node| matches!(sppf.symbol(node), Some(Symbol::Terminal(_))))
+        .map(|node| sppf.token(node).unwrap())
+        .collect();
+    assert!(leaves.contains(&&Spanned {
+        token: PaperToken::Td,
+        span: 6..7,
+    }));
+}
+
+#[test]
+fn graph_trees() {
+    use crate::grammar::gamma1;
+    use std::collections::BTreeSet;
+    // a derivation as Tree::display shows it
+    fn show(s: &S) -> String {
+        match s {
+            S::ASd(a, s) => {
+                let a = if *a == A::A { "a" } else { "c" };
+                format!("(S (A {}) {} d)", a, show(s))
+            }
+            S::BS(b, s) => {
+                let b = if *b == B::A { "a" } else { "b" };
+                format!("(S (B {}) {})", b, show(s))
+            }
+            S::Eps => "(S)".to_owned(),
+        }
+    }
+    // the runtime Γ1 derives what the rules above do, ambiguous inputs included
+    let grammar = gamma1();
+    for input in &["", "ab", "cd", "aabd", "aaabdd", "acbdd", "abaabd"] {
+        let sppf = crate::graph::parse(input.as_bytes()).unwrap().value;
+        let trees: BTreeSet<String> = sppf
+            .trees()
+            .map(|tree| tree.display(&grammar).to_string())
+            .collect();
+        let derivations: BTreeSet<String> = parse(input.as_bytes())
+            .unwrap()
+            .value
+            .iter()
+            .map(show)
+            .collect();
+        assert_eq!(trees, derivations);
+        assert_eq!(sppf.trees().count(), derivations.len());
+    }
+}
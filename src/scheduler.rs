@@ -0,0 +1,109 @@
+use std::collections::{BTreeMap, VecDeque};
+
+/// Order in which the `graph` main loop takes the descriptors from R, see
+/// `graph::parse_scheduled`. The SPPF is the same in any order, the number
+/// of descriptors waiting in R at a time is not.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Scheduler {
+    /// The last descriptor added first, R being a stack.
+    #[default]
+    Lifo,
+    /// The first descriptor added first, R being a queue.
+    Fifo,
+    /// Every descriptor at input position j before those at j + 1, the
+    /// last added first within a position, so the input is read left to
+    /// right once.
+    ByPosition,
+}
+
+// the descriptors in R, in the order of a scheduler
+pub(crate) enum Pending<D> {
+    Lifo(Vec<D>),
+    Fifo(VecDeque<D>),
+    // descriptors by position, and their number
+    ByPosition(BTreeMap<usize, Vec<D>>, usize),
+}
+
+impl<D> Pending<D> {
+    pub(crate) fn new(scheduler: Scheduler) -> Pending<D> {
+        match scheduler {
+            Scheduler::Lifo => Pending::Lifo(Vec::new()),
+            Scheduler::Fifo => Pending::Fifo(VecDeque::new()),
+            Scheduler::ByPosition => Pending::ByPosition(BTreeMap::new(), 0),
+        }
+    }
+
+    pub(crate) fn push(&mut self, position: usize, descriptor: D) {
+        match self {
+            Pending::Lifo(stack) => stack.push(descriptor),
+            Pending::Fifo(queue) => queue.push_back(descriptor),
+            Pending::ByPosition(positions, len) => {
+                positions.entry(position).or_default().push(descriptor);
+                *len += 1;
+            }
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<D> {
+        match self {
+            Pending::Lifo(stack) => stack.pop(),
+            Pending::Fifo(queue) => queue.pop_front(),
+            Pending::ByPosition(positions, len) => {
+                let (&position, descriptors) = positions.iter_mut().next()?;
+                let descriptor = descriptors.pop();
+                if descriptors.is_empty() {
+                    positions.remove(&position);
+                }
+                *len -= 1;
+                descriptor
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Pending::Lifo(stack) => stack.len(),
+            Pending::Fifo(queue) => queue.len(),
+            Pending::ByPosition(_, len) => *len,
+        }
+    }
+}
+
+#[test]
+fn same_sppf() {
+    use crate::grammar::{gamma1, Grammar};
+    use crate::sppf::Sppf;
+    use std::collections::BTreeSet;
+    // each node by its kind and extent, with those of its children
+    fn nodes(sppf: &Sppf) -> BTreeSet<String> {
+        let node = |node| format!("{:?} {:?}", sppf.kind(node), sppf.extent(node));
+        sppf.nodes()
+            .map(|id| {
+                let children: BTreeSet<String> = sppf.children(id).map(node).collect();
+                format!("{} {:?}", node(id), children)
+            })
+            .collect()
+    }
+    let grammars = [
+        (gamma1(), &b"aabd"[..]),
+        (gamma1(), b"aaaaddd"),
+        (
+            Grammar::from_bnf(r#"S ::= S S | "a" ;"#).unwrap(),
+            b"aaaaaa",
+        ),
+        (
+            Grammar::from_bnf(r#"S ::= S S | S | "a" | ;"#).unwrap(),
+            b"aaa",
+        ),
+    ];
+    for (grammar, input) in &grammars {
+        let parse = |scheduler| {
+            let (parsed, _) = crate::graph::parse_scheduled(grammar, input, scheduler);
+            let sppf = parsed.unwrap().value;
+            (sppf.len(), nodes(&sppf))
+        };
+        let lifo = parse(Scheduler::Lifo);
+        assert_eq!(parse(Scheduler::Fifo), lifo);
+        assert_eq!(parse(Scheduler::ByPosition), lifo);
+    }
+}
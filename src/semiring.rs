@@ -0,0 +1,428 @@
+use crate::count::Count;
+use crate::grammar::Symbol;
+use crate::sppf::{NodeId, SPPFNode, Sppf};
+use crate::tree::Tree;
+use num_bigint::BigUint;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{Graph, NodeIndex};
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Index;
+
+/// A semiring to evaluate an SPPF in, see `Sppf::evaluate`.
+///
+/// Alternatives of a node are combined with `add` and the children of a
+/// packed node with `mul`; `production` is applied once for each use of
+/// a production, which is where per-rule weights come in.
+pub trait Semiring {
+    type Value: Clone + PartialEq;
+
+    fn zero(&self) -> Self::Value;
+    fn one(&self) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+
+    /// Value of a terminal node over extent (i, i + 1).
+    fn terminal(&self, _terminal: usize, _extent: (usize, usize)) -> Self::Value {
+        self.one()
+    }
+
+    /// Value of a derivation by `production` over an extent, given the
+    /// product of its children.
+    fn production(
+        &self,
+        _production: usize,
+        _extent: (usize, usize),
+        children: Self::Value,
+    ) -> Self::Value {
+        children
+    }
+
+    /// Value of an error node standing in for a symbol, see
+    /// `graph::parse_recovering`.
+    fn error(&self, _symbol: Symbol, _extent: (usize, usize)) -> Self::Value {
+        self.one()
+    }
+
+    /// Value of the nodes on a cycle of the SPPF. `None` evaluates them by
+    /// iterating from zero until nothing changes, which only terminates
+    /// if the values stop growing, as in the idempotent semirings.
+    fn cycle(&self) -> Option<Self::Value> {
+        None
+    }
+
+    /// Whether to leave out the derivations that revisit a node on a cycle
+    /// of the SPPF, as `Sppf::trees` does, rather than evaluate the cycle.
+    fn cycle_free(&self) -> bool {
+        false
+    }
+}
+
+/// Whether there is a derivation.
+pub struct Boolean;
+
+impl Semiring for Boolean {
+    type Value = bool;
+
+    fn zero(&self) -> bool {
+        false
+    }
+
+    fn one(&self) -> bool {
+        true
+    }
+
+    fn add(&self, a: &bool, b: &bool) -> bool {
+        *a || *b
+    }
+
+    fn mul(&self, a: &bool, b: &bool) -> bool {
+        *a && *b
+    }
+}
+
+/// Number of derivations, infinite on cycles.
+pub struct Counting;
+
+impl Semiring for Counting {
+    type Value = Count;
+
+    fn zero(&self) -> Count {
+        Count::Finite(BigUint::from(0u32))
+    }
+
+    fn one(&self) -> Count {
+        Count::Finite(BigUint::from(1u32))
+    }
+
+    fn add(&self, a: &Count, b: &Count) -> Count {
+        match (a, b) {
+            (Count::Finite(a), Count::Finite(b)) => Count::Finite(a + b),
+            _ => Count::Infinite,
+        }
+    }
+
+    fn mul(&self, a: &Count, b: &Count) -> Count {
+        match (a, b) {
+            (Count::Finite(a), Count::Finite(b)) => Count::Finite(a * b),
+            _ => Count::Infinite,
+        }
+    }
+
+    fn cycle(&self) -> Option<Count> {
+        Some(Count::Infinite)
+    }
+}
+
+/// Cost of the cheapest derivation, adding up the weight of each
+/// production used; infinity if there is none.
+pub struct Tropical {
+    /// Weight per production, all non-negative.
+    pub weights: Vec<f64>,
+}
+
+impl Semiring for Tropical {
+    type Value = f64;
+
+    fn zero(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn one(&self) -> f64 {
+        0.0
+    }
+
+    fn add(&self, a: &f64, b: &f64) -> f64 {
+        a.min(*b)
+    }
+
+    fn mul(&self, a: &f64, b: &f64) -> f64 {
+        a + b
+    }
+
+    fn production(&self, production: usize, _extent: (usize, usize), children: f64) -> f64 {
+        children + self.weights[production]
+    }
+}
+
+/// Probability of the most likely derivation (Viterbi), multiplying the
+/// probability of each production used.
+pub struct MaxProduct {
+    /// Probability per production, all in [0, 1].
+    pub weights: Vec<f64>,
+}
+
+impl Semiring for MaxProduct {
+    type Value = f64;
+
+    fn zero(&self) -> f64 {
+        0.0
+    }
+
+    fn one(&self) -> f64 {
+        1.0
+    }
+
+    fn add(&self, a: &f64, b: &f64) -> f64 {
+        a.max(*b)
+    }
+
+    fn mul(&self, a: &f64, b: &f64) -> f64 {
+        a * b
+    }
+
+    fn production(&self, production: usize, _extent: (usize, usize), children: f64) -> f64 {
+        children * self.weights[production]
+    }
+}
+
+/// All derivation trees, as sets of sibling sequences: the value of a
+/// symbol node holds one-tree sequences. On a cycle, only the trees that do
+/// not revisit a node, those `Sppf::trees` yields.
+pub struct TreeSet;
+
+impl Semiring for TreeSet {
+    type Value = BTreeSet<Vec<Tree>>;
+
+    fn zero(&self) -> Self::Value {
+        BTreeSet::new()
+    }
+
+    fn one(&self) -> Self::Value {
+        let mut one = BTreeSet::new();
+        one.insert(vec![]);
+        one
+    }
+
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value {
+        a.union(b).cloned().collect()
+    }
+
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value {
+        let mut product = BTreeSet::new();
+        for left in a {
+            for right in b {
+                product.insert(left.iter().chain(right).cloned().collect());
+            }
+        }
+        product
+    }
+
+    fn terminal(&self, terminal: usize, extent: (usize, usize)) -> Self::Value {
+        let mut value = BTreeSet::new();
+        value.insert(vec![Tree::Leaf { terminal, extent }]);
+        value
+    }
+
+    fn error(&self, symbol: Symbol, extent: (usize, usize)) -> Self::Value {
+        let mut value = BTreeSet::new();
+        value.insert(vec![Tree::Error { symbol, extent }]);
+        value
+    }
+
+    fn production(
+        &self,
+        production: usize,
+        extent: (usize, usize),
+        children: Self::Value,
+    ) -> Self::Value {
+        children
+            .into_iter()
+            .map(|children| {
+                vec![Tree::Node {
+                    production,
+                    extent,
+                    children,
+                }]
+            })
+            .collect()
+    }
+
+    fn cycle_free(&self) -> bool {
+        true
+    }
+}
+
+/// Values of every node of an SPPF in some semiring.
+#[derive(Clone, Debug)]
+pub struct Values<V> {
+    pub(crate) values: Vec<V>,
+}
+
+impl<V> Index<NodeId> for Values<V> {
+    type Output = V;
+
+    fn index(&self, node: NodeId) -> &V {
+        &self.values[node.0]
+    }
+}
+
+impl<T> Sppf<T> {
+    /// Evaluate every node bottom-up: packed nodes multiply their
+    /// children, symbol and intermediate nodes add up their packed
+    /// children, and eps is one.
+    pub fn evaluate_all<S: Semiring>(&self, semiring: &S) -> Values<S::Value> {
+        let mut graph: Graph<(), ()> = Graph::with_capacity(self.nodes.len(), 0);
+        for _ in 0..self.nodes.len() {
+            graph.add_node(());
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            for &child in node.children().into_iter().flatten() {
+                graph.add_edge(NodeIndex::new(index), NodeIndex::new(child), ());
+            }
+        }
+
+        let mut values = vec![semiring.zero(); self.nodes.len()];
+        // strongly connected components come children first
+        for component in tarjan_scc(&graph) {
+            let first = component[0].index();
+            let cyclic = component.len() > 1
+                || matches!(self.nodes[first].children(), Some(children) if children.contains(&first));
+            if !cyclic {
+                values[first] = self.value(semiring, first, &mut |child| values[child].clone());
+                continue;
+            }
+            if semiring.cycle_free() {
+                let on_cycle: HashSet<usize> = component.iter().map(|node| node.index()).collect();
+                for node in &component {
+                    let value =
+                        self.path_value(semiring, &values, &on_cycle, node.index(), &mut vec![]);
+                    values[node.index()] = value;
+                }
+                continue;
+            }
+            if let Some(value) = semiring.cycle() {
+                for node in component {
+                    values[node.index()] = value.clone();
+                }
+                continue;
+            }
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for node in &component {
+                    let value =
+                        self.value(semiring, node.index(), &mut |child| values[child].clone());
+                    if value != values[node.index()] {
+                        values[node.index()] = value;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Values { values }
+    }
+
+    /// Value of the whole input, zero if the parse failed.
+    pub fn evaluate<S: Semiring>(&self, semiring: &S) -> S::Value {
+        match self.root() {
+            Some(root) => self.evaluate_all(semiring)[root].clone(),
+            None => semiring.zero(),
+        }
+    }
+
+    // value of a node from the values of its children
+    fn value<S: Semiring>(
+        &self,
+        semiring: &S,
+        index: usize,
+        child: &mut dyn FnMut(usize) -> S::Value,
+    ) -> S::Value {
+        match &self.nodes[index] {
+            SPPFNode::Dummy => semiring.zero(),
+            SPPFNode::Symbol(Symbol::Terminal(terminal), i, j, _) => {
+                semiring.terminal(*terminal, (*i, *j))
+            }
+            SPPFNode::Symbol(Symbol::Eps, _, _, _) => semiring.one(),
+            SPPFNode::Error(symbol, i, j) => semiring.error(*symbol, (*i, *j)),
+            SPPFNode::Symbol(_, i, j, children) => {
+                children.iter().fold(semiring.zero(), |sum, &packed| {
+                    let production = match &self.nodes[packed] {
+                        SPPFNode::Packed(slot, _, _) => slot.production,
+                        _ => unreachable!(),
+                    };
+                    let value = semiring.production(production, (*i, *j), child(packed));
+                    semiring.add(&sum, &value)
+                })
+            }
+            SPPFNode::Intermediate(_, _, _, children) => {
+                children.iter().fold(semiring.zero(), |sum, &packed| {
+                    semiring.add(&sum, &child(packed))
+                })
+            }
+            SPPFNode::Packed(_, _, children) => {
+                children.iter().fold(semiring.one(), |product, &node| {
+                    semiring.mul(&product, &child(node))
+                })
+            }
+        }
+    }
+
+    // value of a node on a cycle from its derivations that revisit no node
+    // of path, those off the cycle having their value already
+    fn path_value<S: Semiring>(
+        &self,
+        semiring: &S,
+        values: &[S::Value],
+        on_cycle: &HashSet<usize>,
+        index: usize,
+        path: &mut Vec<usize>,
+    ) -> S::Value {
+        if !on_cycle.contains(&index) {
+            return values[index].clone();
+        }
+        if path.contains(&index) {
+            return semiring.zero();
+        }
+        path.push(index);
+        let value = self.value(semiring, index, &mut |child| {
+            self.path_value(semiring, values, on_cycle, child, path)
+        });
+        path.pop();
+        value
+    }
+}
+
+#[test]
+fn semirings() {
+    use crate::grammar::{gamma1, Grammar};
+    let grammar = gamma1();
+    let sppf = crate::graph::parse_with(&grammar, b"aabd").unwrap().value;
+    assert!(sppf.evaluate(&Boolean));
+    assert_eq!(sppf.evaluate(&Counting), sppf.count());
+    let trees: BTreeSet<Vec<Tree>> = sppf.trees().map(|tree| vec![tree]).collect();
+    assert_eq!(sppf.evaluate(&TreeSet), trees);
+
+    let text = r#"S ::= A | B ; A ::= "a" ; B ::= "a" ;"#;
+    let grammar = Grammar::from_bnf(text).unwrap();
+    let sppf = crate::graph::parse_with(&grammar, b"a").unwrap().value;
+    // S ::= A, S ::= B, A ::= a, B ::= a
+    let tropical = Tropical {
+        weights: vec![3.0, 2.0, 0.5, 1.0],
+    };
+    assert_eq!(sppf.evaluate(&tropical), 3.0);
+    let viterbi = MaxProduct {
+        weights: vec![0.3, 0.7, 1.0, 0.5],
+    };
+    assert_eq!(sppf.evaluate(&viterbi), 0.35);
+
+    let grammar = Grammar::from_bnf(r#"S ::= S | "a" ;"#).unwrap();
+    let sppf = crate::graph::parse_with(&grammar, b"a").unwrap().value;
+    assert!(sppf.evaluate(&Boolean));
+    assert_eq!(sppf.evaluate(&Counting), Count::Infinite);
+    let tropical = Tropical {
+        weights: vec![1.0, 2.0],
+    };
+    assert_eq!(sppf.evaluate(&tropical), 2.0);
+
+    // on cycles, the trees that revisit no node
+    for &(text, input) in &[
+        (r#"S ::= S | "a" ;"#, &b"a"[..]),
+        (r#"S ::= S S | "a" | ;"#, b"aa"),
+    ] {
+        let grammar = Grammar::from_bnf(text).unwrap();
+        let sppf = crate::graph::parse_with(&grammar, input).unwrap().value;
+        let trees: BTreeSet<Vec<Tree>> = sppf.trees().map(|tree| vec![tree]).collect();
+        assert!(!trees.is_empty());
+        assert_eq!(sppf.evaluate(&TreeSet), trees);
+    }
+}
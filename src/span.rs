@@ -0,0 +1,75 @@
+use std::ops::Range;
+
+/// A syntax tree node with the byte range of its source text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Located<T> {
+    pub node: T,
+    pub span: Range<usize>,
+}
+
+/// Start of each line of a text, for turning byte offsets into 1-based
+/// lines and columns, columns counting characters.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> LineIndex<'a> {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(index, _)| index + 1));
+        LineIndex { text, starts }
+    }
+
+    /// Line and column of a byte offset, which may be the end of the text.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let column = self.text[self.starts[line]..offset].chars().count();
+        (line + 1, column + 1)
+    }
+
+    /// Lines and columns of both ends of a byte range.
+    pub fn range(&self, span: Range<usize>) -> ((usize, usize), (usize, usize)) {
+        (self.line_col(span.start), self.line_col(span.end))
+    }
+}
+
+#[test]
+fn sppf_spans() {
+    use crate::grammar::{gamma1, Symbol};
+    use crate::pg::lex;
+    let source = "a a b  d";
+    let tokens = lex(source);
+    let sppf = crate::graph::parse(&tokens).unwrap().value;
+    let root = sppf.root().unwrap();
+    assert_eq!(sppf.extent(root), (0, 4));
+    assert_eq!(sppf.span(root), 0..8);
+
+    let d = sppf
+        .nodes()
+        .find(|&node| sppf.symbol(node) == Some(Symbol::Terminal(3)))
+        .unwrap();
+    assert_eq!(gamma1().terminals[3], "d");
+    assert_eq!(sppf.span(d), 7..8);
+    // the innermost S ::= eps is empty, just before d
+    let eps = sppf
+        .nodes()
+        .find(|&node| {
+            sppf.symbol(node) == Some(Symbol::Nonterminal(0)) && sppf.extent(node) == (3, 3)
+        })
+        .unwrap();
+    assert_eq!(sppf.span(eps), 7..7);
+
+    let index = LineIndex::new("ab\ncé\n");
+    assert_eq!(index.line_col(0), (1, 1));
+    assert_eq!(index.range(4..6), ((2, 2), (2, 3)));
+    assert_eq!(index.line_col(7), (3, 1));
+
+    let ss = crate::future::parse(b"aabd").unwrap().value;
+    let crate::common::SS::S(s) = ss;
+    assert_eq!(s.span, 0..4);
+}
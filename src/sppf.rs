@@ -0,0 +1,221 @@
+use crate::grammar::{Slot, Symbol};
+use crate::input::{Input, Token};
+use std::ops::Range;
+
+pub(crate) type SPPFNodeIndex = usize;
+
+// index of the dummy node $
+pub(crate) const DUMMY: SPPFNodeIndex = 0;
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum SPPFNode {
+    Dummy,
+    // usize, usize: from, to
+    // Vec<SPPFNodeIndex>: children
+    Symbol(Symbol, usize, usize, Vec<SPPFNodeIndex>),
+    Intermediate(Slot, usize, usize, Vec<SPPFNodeIndex>),
+    Packed(Slot, usize, Vec<SPPFNodeIndex>),
+    // symbol that could not be matched at from, skipping input up to to
+    Error(Symbol, usize, usize),
+}
+
+impl SPPFNode {
+    pub(crate) fn kind(&self) -> NodeKind {
+        use SPPFNode::*;
+        match self {
+            Symbol(s, _, _, _) => NodeKind::Symbol(*s),
+            Intermediate(l, _, _, _) => NodeKind::Intermediate(*l),
+            Packed(l, k, _) => NodeKind::Packed(*l, *k),
+            Error(s, _, _) => NodeKind::Error(*s),
+            Dummy => unreachable!(),
+        }
+    }
+
+    // (from, to), None for the dummy node and for packed nodes, which
+    // span their children
+    pub(crate) fn extent(&self) -> Option<(usize, usize)> {
+        use SPPFNode::*;
+        match self {
+            Symbol(_, l, r, _) | Intermediate(_, l, r, _) | Error(_, l, r) => Some((*l, *r)),
+            Dummy | Packed(_, _, _) => None,
+        }
+    }
+
+    pub(crate) fn children(&self) -> Option<&Vec<SPPFNodeIndex>> {
+        use SPPFNode::*;
+        match self {
+            Dummy | Error(_, _, _) => None,
+            Symbol(_, _, _, children) => Some(children),
+            Intermediate(_, _, _, children) => Some(children),
+            Packed(_, _, children) => Some(children),
+        }
+    }
+
+    pub(crate) fn children_mut(&mut self) -> Option<&mut Vec<SPPFNodeIndex>> {
+        use SPPFNode::*;
+        match self {
+            Dummy | Error(_, _, _) => None,
+            Symbol(_, _, _, children) => Some(children),
+            Intermediate(_, _, _, children) => Some(children),
+            Packed(_, _, children) => Some(children),
+        }
+    }
+}
+
+/// Handle of a node in an `Sppf`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NodeId(pub(crate) SPPFNodeIndex);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NodeKind {
+    /// Terminal, nonterminal or eps node; its children are packed nodes.
+    Symbol(Symbol),
+    /// Intermediate node for the slot `X ::= α . β`; its children are packed nodes.
+    Intermediate(Slot),
+    /// Packed node for a slot with pivot k; its children are one or two
+    /// symbol or intermediate nodes.
+    Packed(Slot, usize),
+    /// Error node left by `graph::parse_recovering` where a symbol was
+    /// expected, spanning the input skipped in its place; it has no children.
+    Error(Symbol),
+}
+
+/// Shared packed parse forest built by `graph::parse`, keeping the input
+/// tokens for its terminal nodes.
+#[derive(Debug, Clone)]
+pub struct Sppf<T = u8> {
+    pub(crate) nodes: Vec<SPPFNode>,
+    pub(crate) root: Option<SPPFNodeIndex>,
+    pub(crate) tokens: Vec<T>,
+}
+
+impl<T> Sppf<T> {
+    /// The start symbol node spanning the whole input, `None` if the parse failed.
+    pub fn root(&self) -> Option<NodeId> {
+        self.root.map(NodeId)
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All nodes, in creation order.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> {
+        (1..self.nodes.len()).map(NodeId)
+    }
+
+    pub fn kind(&self, node: NodeId) -> NodeKind {
+        self.nodes[node.0].kind()
+    }
+
+    /// Grammar symbol of a symbol node.
+    pub fn symbol(&self, node: NodeId) -> Option<Symbol> {
+        match self.kind(node) {
+            NodeKind::Symbol(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Grammar slot of an intermediate or packed node.
+    pub fn slot(&self, node: NodeId) -> Option<Slot> {
+        match self.kind(node) {
+            NodeKind::Intermediate(l) | NodeKind::Packed(l, _) => Some(l),
+            NodeKind::Symbol(_) | NodeKind::Error(_) => None,
+        }
+    }
+
+    /// Left and right extent (i, j): the node derives input[i..j].
+    pub fn extent(&self, node: NodeId) -> (usize, usize) {
+        let extent = |index: SPPFNodeIndex| self.nodes[index].extent().unwrap();
+        match &self.nodes[node.0] {
+            SPPFNode::Packed(_, _, children) => (
+                extent(children[0]).0,
+                extent(children[children.len() - 1]).1,
+            ),
+            _ => extent(node.0),
+        }
+    }
+
+    /// Input token of a terminal node.
+    pub fn token(&self, node: NodeId) -> Option<&T> {
+        match &self.nodes[node.0] {
+            SPPFNode::Symbol(Symbol::Terminal(_), i, _, _) => Some(&self.tokens[*i]),
+            _ => None,
+        }
+    }
+
+    pub fn children(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes[node.0]
+            .children()
+            .into_iter()
+            .flatten()
+            .map(|child| NodeId(*child))
+    }
+
+    /// Whether a symbol or intermediate node has more than one packed child.
+    pub fn is_ambiguous(&self, node: NodeId) -> bool {
+        match &self.nodes[node.0] {
+            SPPFNode::Symbol(_, _, _, children) | SPPFNode::Intermediate(_, _, _, children) => {
+                children.len() > 1
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<T: Token> Sppf<T> {
+    /// Byte range of a node in the source text, from the token spans.
+    pub fn span(&self, node: NodeId) -> Range<usize> {
+        self.tokens[..].source_span(self.extent(node))
+    }
+}
+
+#[test]
+fn navigate() {
+    use crate::grammar::gamma1;
+    use std::collections::BTreeSet;
+    let grammar = gamma1();
+    let sppf = crate::graph::parse_with(&grammar, b"aabd").unwrap().value;
+    let root = sppf.root().unwrap();
+    assert_eq!(sppf.symbol(root), Some(Symbol::Nonterminal(grammar.start)));
+    assert_eq!(sppf.extent(root), (0, 4));
+    // S ::= B S . and S ::= A S d .
+    assert!(sppf.is_ambiguous(root));
+    let slots: BTreeSet<Slot> = sppf
+        .children(root)
+        .map(|packed| sppf.slot(packed).unwrap())
+        .collect();
+    let expected: BTreeSet<Slot> = [(0, 3), (1, 2)]
+        .iter()
+        .map(|&(production, dot)| Slot { production, dot })
+        .collect();
+    assert_eq!(slots, expected);
+    for packed in sppf.children(root) {
+        assert_eq!(sppf.extent(packed), (0, 4));
+    }
+
+    // every kind of node has an extent, error nodes included
+    let recovered = crate::graph::parse_recovering(&grammar, b"axd")
+        .unwrap()
+        .value;
+    let sppf = recovered.sppf;
+    let kinds: BTreeSet<u8> = sppf
+        .nodes()
+        .map(|node| {
+            let (i, j) = sppf.extent(node);
+            assert!(i <= j && j <= 3);
+            match sppf.kind(node) {
+                NodeKind::Symbol(_) => 0,
+                NodeKind::Intermediate(_) => 1,
+                NodeKind::Packed(_, _) => 2,
+                NodeKind::Error(_) => 3,
+            }
+        })
+        .collect();
+    assert_eq!(kinds.len(), 4);
+}
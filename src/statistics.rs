@@ -0,0 +1,111 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Counters of a parse by the `graph` main loop, see
+/// `graph::parse_with_statistics`.
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    /// Descriptors added to R.
+    pub descriptors_added: usize,
+    /// Descriptors taken from R and processed.
+    pub descriptors_processed: usize,
+    /// Descriptors not added again because they were already in U_j.
+    pub duplicates: usize,
+    pub gss_nodes: usize,
+    pub gss_edges: usize,
+    /// Calls of pop, the initial GSS node included.
+    pub pops: usize,
+    pub symbol_nodes: usize,
+    pub intermediate_nodes: usize,
+    pub packed_nodes: usize,
+    pub memory: Memory,
+    pub time: Timings,
+}
+
+/// Peak size in bytes of each structure of the main loop, estimated from
+/// its number of entries and the size of one, leaving out the overhead of
+/// the collections holding them.
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    /// Descriptors pending in R.
+    pub todo: usize,
+    /// The sets U_j of descriptors seen.
+    pub visited: usize,
+    /// The SPPF nodes popped from each GSS node, P.
+    pub popped: usize,
+    pub gss: usize,
+    /// The SPPF before filtering, with the maps finding its nodes.
+    pub sppf: usize,
+}
+
+/// Wall time of each phase of a parse.
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    /// Grammar analysis and the lookahead at each position.
+    pub analysis: Duration,
+    /// The main loop, error recovery included.
+    pub parse: Duration,
+    /// Building the result from the forest, filtering the SPPF.
+    pub build: Duration,
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "descriptors: {} added, {} processed, {} duplicates",
+            self.descriptors_added, self.descriptors_processed, self.duplicates
+        )?;
+        writeln!(
+            f,
+            "GSS: {} nodes, {} edges, {} pops",
+            self.gss_nodes, self.gss_edges, self.pops
+        )?;
+        writeln!(
+            f,
+            "SPPF: {} symbol, {} intermediate, {} packed nodes",
+            self.symbol_nodes, self.intermediate_nodes, self.packed_nodes
+        )?;
+        let memory = &self.memory;
+        writeln!(
+            f,
+            "peak bytes: R {}, U {}, P {}, GSS {}, SPPF {}",
+            memory.todo, memory.visited, memory.popped, memory.gss, memory.sppf
+        )?;
+        let time = &self.time;
+        write!(
+            f,
+            "time: analysis {:?}, parse {:?}, build {:?}",
+            time.analysis, time.parse, time.build
+        )
+    }
+}
+
+#[test]
+fn cubic() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let (outcome, statistics) = crate::graph::parse_with_statistics(&grammar, b"aabd");
+    let sppf = outcome.unwrap().value;
+    assert_eq!(
+        statistics.descriptors_added,
+        statistics.descriptors_processed
+    );
+    assert_eq!(
+        statistics.symbol_nodes + statistics.intermediate_nodes + statistics.packed_nodes,
+        sppf.len()
+    );
+
+    // a^n d^n takes O(n^3) steps, so doubling n takes at most 8 times as many
+    let statistics = |n: usize| {
+        let mut input = vec![b'a'; n];
+        input.extend(vec![b'd'; n]);
+        let (outcome, statistics) = crate::graph::parse_with_statistics(&grammar, &input);
+        assert!(outcome.is_ok());
+        statistics
+    };
+    let (small, large) = (statistics(16), statistics(32));
+    assert!(large.descriptors_processed <= 8 * small.descriptors_processed);
+    assert!(large.packed_nodes <= 8 * small.packed_nodes);
+    assert!(large.gss_edges <= 8 * small.gss_edges);
+}
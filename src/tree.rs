@@ -0,0 +1,343 @@
+use crate::grammar::{Ebnf, Grammar, Symbol};
+use crate::sppf::{NodeId, NodeKind, Sppf};
+use std::fmt;
+
+/// A derivation tree, with intermediate nodes of the SPPF collapsed into
+/// the children of their production.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Tree {
+    Leaf {
+        terminal: usize,
+        extent: (usize, usize),
+    },
+    Node {
+        production: usize,
+        extent: (usize, usize),
+        children: Vec<Tree>,
+    },
+    /// The items of an EBNF repetition, see `Tree::resugar`.
+    List {
+        extent: (usize, usize),
+        items: Vec<Tree>,
+    },
+    /// An expected symbol replaced by the skipped input, see
+    /// `graph::parse_recovering`.
+    Error {
+        symbol: Symbol,
+        extent: (usize, usize),
+    },
+}
+
+impl Tree {
+    pub fn extent(&self) -> (usize, usize) {
+        match self {
+            Tree::Leaf { extent, .. }
+            | Tree::Node { extent, .. }
+            | Tree::List { extent, .. }
+            | Tree::Error { extent, .. } => *extent,
+        }
+    }
+
+    /// Undo the desugaring of EBNF operators: repetitions become lists of
+    /// their items without separators, while groups and optionals are
+    /// spliced into the node that uses them. A group repeated by a list
+    /// stays a node per item.
+    pub fn resugar(self, grammar: &Grammar) -> Tree {
+        let mut trees = vec![];
+        splice(self, grammar, &mut trees);
+        trees.pop().unwrap()
+    }
+
+    pub fn display<'a>(&'a self, grammar: &'a Grammar) -> DisplayTree<'a> {
+        DisplayTree {
+            grammar,
+            tree: self,
+        }
+    }
+}
+
+fn ebnf(grammar: &Grammar, production: usize) -> Option<Ebnf> {
+    grammar
+        .ebnf
+        .get(&grammar.productions[production].lhs)
+        .cloned()
+}
+
+// push the resugared form of tree, which is any number of trees for
+// spliced groups and optionals
+fn splice(tree: Tree, grammar: &Grammar, out: &mut Vec<Tree>) {
+    match tree {
+        Tree::Node {
+            production,
+            extent,
+            children,
+        } => match ebnf(grammar, production) {
+            None => {
+                let mut spliced = vec![];
+                for child in children {
+                    splice(child, grammar, &mut spliced);
+                }
+                out.push(Tree::Node {
+                    production,
+                    extent,
+                    children: spliced,
+                });
+            }
+            Some(Ebnf::Group) | Some(Ebnf::Optional) => {
+                for child in children {
+                    splice(child, grammar, out);
+                }
+            }
+            Some(Ebnf::List) => {
+                let mut items = vec![];
+                list_items(children, grammar, &mut items);
+                out.push(Tree::List { extent, items });
+            }
+        },
+        tree => out.push(tree),
+    }
+}
+
+// items of a list production such as X ::= x s X: the first child is an
+// item, a later last child continues the list and the rest are separators
+fn list_items(children: Vec<Tree>, grammar: &Grammar, items: &mut Vec<Tree>) {
+    let len = children.len();
+    for (index, child) in children.into_iter().enumerate() {
+        match child {
+            Tree::Node { children, .. } if index > 0 && index == len - 1 => {
+                list_items(children, grammar, items)
+            }
+            Tree::Node {
+                production,
+                extent,
+                children,
+            } if index == 0 && ebnf(grammar, production) == Some(Ebnf::Group) => {
+                let mut spliced = vec![];
+                for child in children {
+                    splice(child, grammar, &mut spliced);
+                }
+                items.push(Tree::Node {
+                    production,
+                    extent,
+                    children: spliced,
+                });
+            }
+            child if index == 0 => splice(child, grammar, items),
+            _ => {}
+        }
+    }
+}
+
+/// Formats a tree as an s-expression, e.g. `(S (A a) (S) d)`, with lists
+/// in brackets and error nodes as `(error d)`.
+pub struct DisplayTree<'a> {
+    grammar: &'a Grammar,
+    tree: &'a Tree,
+}
+
+impl<'a> fmt::Display for DisplayTree<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.tree {
+            Tree::Leaf { terminal, .. } => write!(f, "{}", self.grammar.terminals[*terminal]),
+            Tree::Node {
+                production,
+                children,
+                ..
+            } => {
+                let lhs = self.grammar.productions[*production].lhs;
+                write!(f, "({}", self.grammar.nonterminals[lhs])?;
+                for child in children {
+                    write!(f, " {}", child.display(self.grammar))?;
+                }
+                write!(f, ")")
+            }
+            Tree::List { items, .. } => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item.display(self.grammar))?;
+                }
+                write!(f, "]")
+            }
+            Tree::Error { symbol, .. } => {
+                write!(f, "(error {})", self.grammar.symbol_name(*symbol))
+            }
+        }
+    }
+}
+
+/// Iterator over the derivation trees of an SPPF.
+///
+/// Trees are built one at a time: each ambiguous node met in depth-first
+/// order records which packed child was taken, and the next tree advances
+/// the last of these choices like an odometer. Derivations that would
+/// revisit a node of a cyclic SPPF are skipped, so only the finitely many
+/// cycle-free trees are produced.
+pub struct Trees<'a, T> {
+    sppf: &'a Sppf<T>,
+    // (chosen packed child, number of packed children) per ambiguous node
+    choices: Vec<(usize, usize)>,
+    done: bool,
+    limit: Option<usize>,
+}
+
+impl<T> Sppf<T> {
+    pub fn trees(&self) -> Trees<'_, T> {
+        Trees {
+            sppf: self,
+            choices: vec![],
+            done: self.root().is_none(),
+            limit: None,
+        }
+    }
+}
+
+impl<'a, T> Trees<'a, T> {
+    /// Stop after `limit` trees.
+    pub fn limit(mut self, limit: usize) -> Trees<'a, T> {
+        self.limit = Some(limit);
+        self
+    }
+
+    // advance the last choice that has alternatives left, dropping the later ones
+    fn advance(&mut self) {
+        while let Some((chosen, count)) = self.choices.pop() {
+            if chosen + 1 < count {
+                self.choices.push((chosen + 1, count));
+                return;
+            }
+        }
+        self.done = true;
+    }
+
+    // pick the packed child of a symbol or intermediate node
+    fn choose(&mut self, node: NodeId, cursor: &mut usize) -> NodeId {
+        let mut packed = self.sppf.children(node);
+        if !self.sppf.is_ambiguous(node) {
+            return packed.next().unwrap();
+        }
+        if *cursor == self.choices.len() {
+            self.choices.push((0, self.sppf.children(node).count()));
+        }
+        let chosen = self.choices[*cursor].0;
+        *cursor += 1;
+        packed.nth(chosen).unwrap()
+    }
+
+    fn build(&mut self, node: NodeId, path: &mut Vec<NodeId>, cursor: &mut usize) -> Option<Tree> {
+        let extent = self.sppf.extent(node);
+        match self.sppf.kind(node) {
+            NodeKind::Symbol(Symbol::Terminal(terminal)) => Some(Tree::Leaf { terminal, extent }),
+            NodeKind::Error(symbol) => Some(Tree::Error { symbol, extent }),
+            NodeKind::Symbol(_) => {
+                if path.contains(&node) {
+                    return None;
+                }
+                path.push(node);
+                let packed = self.choose(node, cursor);
+                let production = self.sppf.slot(packed).unwrap().production;
+                let mut children = vec![];
+                let built = self.collect(packed, path, cursor, &mut children);
+                path.pop();
+                built.map(|_| Tree::Node {
+                    production,
+                    extent,
+                    children,
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // append the trees below a packed node, flattening intermediate nodes
+    fn collect(
+        &mut self,
+        packed: NodeId,
+        path: &mut Vec<NodeId>,
+        cursor: &mut usize,
+        children: &mut Vec<Tree>,
+    ) -> Option<()> {
+        for child in self.sppf.children(packed).collect::<Vec<_>>() {
+            match self.sppf.kind(child) {
+                NodeKind::Intermediate(_) => {
+                    if path.contains(&child) {
+                        return None;
+                    }
+                    path.push(child);
+                    let packed = self.choose(child, cursor);
+                    let built = self.collect(packed, path, cursor, children);
+                    path.pop();
+                    built?;
+                }
+                NodeKind::Symbol(Symbol::Eps) => {}
+                _ => children.push(self.build(child, path, cursor)?),
+            }
+        }
+        Some(())
+    }
+}
+
+impl<'a, T> Iterator for Trees<'a, T> {
+    type Item = Tree;
+
+    fn next(&mut self) -> Option<Tree> {
+        if self.limit == Some(0) {
+            return None;
+        }
+        while !self.done {
+            let root = self.sppf.root().unwrap();
+            let tree = self.build(root, &mut vec![], &mut 0);
+            self.advance();
+            if tree.is_some() {
+                self.limit = self.limit.map(|limit| limit - 1);
+                return tree;
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn gamma1_trees() {
+    use crate::grammar::gamma1;
+    let grammar = gamma1();
+    let sppf = crate::graph::parse_with(&grammar, b"aabd").unwrap().value;
+    let mut trees: Vec<String> = sppf
+        .trees()
+        .map(|tree| tree.display(&grammar).to_string())
+        .collect();
+    trees.sort();
+    // the two derivations found by pg::Parser::parse
+    assert_eq!(
+        trees,
+        [
+            "(S (A a) (S (B a) (S (B b) (S))) d)",
+            "(S (B a) (S (A a) (S (B b) (S)) d))",
+        ]
+    );
+    assert_eq!(sppf.trees().limit(1).count(), 1);
+}
+
+#[test]
+fn ebnf_trees() {
+    let text = r#"
+        L ::= "[" E ** "," "]" ;
+        E ::= "x" | L | ("y" "z")+ ;
+    "#;
+    let grammar = Grammar::from_bnf(text).unwrap();
+    let parse = |input: &[u8]| {
+        let sppf = crate::graph::parse_with(&grammar, input).unwrap().value;
+        let trees: Vec<String> = sppf
+            .trees()
+            .map(|tree| tree.resugar(&grammar).display(&grammar).to_string())
+            .collect();
+        trees
+    };
+    assert_eq!(parse(b"[]"), ["(L [ [] ])"]);
+    assert_eq!(
+        parse(b"[x,[x],yzyz]"),
+        ["(L [ [(E x) (E (L [ [(E x)] ])) (E [((\"y\" \"z\") y z) ((\"y\" \"z\") y z)])] ])"]
+    );
+}